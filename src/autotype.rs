@@ -0,0 +1,196 @@
+use ::{Database, Entry, Error, Uuid};
+use xml;
+
+use elementtree::Element;
+
+/// The sequence KeePass falls back to when neither the entry nor any of its
+/// groups specify one.
+pub const DEFAULT_SEQUENCE : &str = "{USERNAME}{TAB}{PASSWORD}{ENTER}";
+
+/// A window title pattern and the keystroke sequence to type into it.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AutoTypeAssociation {
+    pub window      : String,
+    pub sequence    : String,
+}
+
+impl AutoTypeAssociation {
+    /// Checks whether `title` matches the window pattern. Matching is case
+    /// insensitive and `*` matches any run of characters.
+    pub fn matches(&self, title : &str) -> bool {
+        let pattern : Vec<char> = self.window.to_lowercase().chars().collect();
+        let title : Vec<char> = title.to_lowercase().chars().collect();
+        wildcard_match(&pattern, &title)
+    }
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters.
+/// On a mismatch it only backtracks to the last `*`, so it runs in
+/// `O(pattern * text)` time however many stars the pattern has.
+fn wildcard_match(pattern : &[char], text : &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+
+    while t < text.len() {
+        if p < pattern.len() && '*' == pattern[p] {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&x| '*' == x)
+}
+
+/// The `<AutoType>` block of an entry.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AutoType {
+    pub enabled                     : bool,
+    pub data_transfer_obfuscation   : u32,
+
+    /// The entry's own default sequence. Empty means it is inherited from
+    /// the group.
+    pub default_sequence            : String,
+    pub associations                : Vec<AutoTypeAssociation>,
+}
+
+impl AutoType {
+    pub fn new() -> AutoType {
+        AutoType {
+            enabled: true,
+            data_transfer_obfuscation: 0,
+            default_sequence: String::new(),
+            associations: vec![],
+        }
+    }
+
+    pub fn from_xml(e : &Element) -> Result<AutoType, Error> {
+        let enabled = match xml::child_text(e, "Enabled") {
+            None    => true,
+            Some(x) => xml::parse_bool(x)
+                       .ok_or_else(|| Error::new("malformed auto-type flag"))?,
+        };
+
+        let obfuscation = match xml::child_text(e, "DataTransferObfuscation") {
+            None    => 0,
+            Some(x) => x.trim().parse()
+                       .map_err(|_| Error::new("malformed auto-type obfuscation"))?,
+        };
+
+        let associations = e.find_all("Association")
+            .map(|x| AutoTypeAssociation {
+                window: xml::child_text(x, "Window").unwrap_or("").to_owned(),
+                sequence: xml::child_text(x, "KeystrokeSequence")
+                          .unwrap_or("")
+                          .to_owned(),
+            })
+            .collect();
+
+        Ok(AutoType {
+            enabled,
+            data_transfer_obfuscation: obfuscation,
+            default_sequence: xml::child_text(e, "DefaultSequence")
+                              .unwrap_or("")
+                              .to_owned(),
+            associations,
+        })
+    }
+
+    /// Writes the fields into an `<AutoType>` element, replacing any
+    /// existing associations.
+    pub fn write_xml(&self, e : &mut Element) {
+        xml::set_child_text(e, "Enabled", xml::format_bool(self.enabled));
+        xml::set_child_text(e, "DataTransferObfuscation",
+                            self.data_transfer_obfuscation.to_string());
+        xml::set_child_text(e, "DefaultSequence", self.default_sequence.clone());
+
+        while let Some(idx) = e.children()
+                               .position(|x| x.tag().name() == "Association") {
+            e.remove_child(idx);
+        }
+
+        for assoc in &self.associations {
+            let child = e.append_new_child("Association");
+            xml::set_child_text(child, "Window", assoc.window.clone());
+            xml::set_child_text(child, "KeystrokeSequence", assoc.sequence.clone());
+        }
+    }
+
+    /// Returns the first association matching the window title.
+    pub fn association_for(&self, title : &str) -> Option<&AutoTypeAssociation> {
+        self.associations.iter().find(|x| x.matches(title))
+    }
+}
+
+impl Default for AutoType {
+    fn default() -> AutoType {
+        AutoType::new()
+    }
+}
+
+impl Database {
+    /// Returns the effective sequence to type into the window with the given
+    /// title, or `None` if auto-type is disabled for the entry or it doesn't
+    /// match the window.
+    ///
+    /// Like KeePass, an entry matches if one of its associations does, or
+    /// failing that, if the window title contains the entry's title. The
+    /// latter uses the default sequence.
+    pub fn auto_type_sequence(&self, uuid : &Uuid, title : &str)
+                              -> Result<Option<String>, Error> {
+        let entry = self.entry(uuid)
+                        .ok_or_else(|| Error::new("entry not found"))?;
+
+        if !self.auto_type_enabled(uuid)? {
+            return Ok(None);
+        }
+
+        let sequence = match entry.auto_type()?.association_for(title) {
+            Some(x) => x.sequence.clone(),
+            None    => {
+                let name = entry.title().unwrap_or("").trim().to_lowercase();
+                if name.is_empty() || !title.to_lowercase().contains(&name) {
+                    return Ok(None);
+                }
+                String::new()
+            },
+        };
+
+        if sequence.is_empty() {
+            self.default_auto_type_sequence(uuid).map(Some)
+        } else {
+            Ok(Some(sequence))
+        }
+    }
+
+    /// Returns every entry that `auto_type_sequence` would type into the
+    /// window, through an association or the title fallback, along with the
+    /// sequence to type.
+    pub fn auto_type_matches(&self, title : &str)
+                             -> Result<Vec<(Entry<'_>, String)>, Error> {
+        let mut matches = Vec::new();
+
+        for entry in self.entries() {
+            let uuid = match entry.uuid() {
+                None    => continue,
+                Some(x) => x,
+            };
+
+            if let Some(seq) = self.auto_type_sequence(&uuid, title)? {
+                matches.push((entry, seq));
+            }
+        }
+
+        Ok(matches)
+    }
+}
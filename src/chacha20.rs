@@ -0,0 +1,159 @@
+use ::Error;
+
+use byteorder::{LittleEndian, ByteOrder};
+
+const SIGMA : [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// The ChaCha20 stream cipher from RFC 7539, as used to protect values
+/// inside KDBX 4 files.
+pub struct ChaCha20 {
+    state       : [u32; 16],
+    block       : [u8; 64],
+    pos         : usize,
+
+    /// Set once the 32 bit block counter has been used up. Wrapping around
+    /// would reuse the key stream.
+    exhausted   : bool,
+}
+
+fn quarter_round(x : &mut [u32; 16], a : usize, b : usize, c : usize, d : usize) {
+    x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+impl ChaCha20 {
+    pub fn new(key : &[u8], nonce : &[u8]) -> ChaCha20 {
+        ChaCha20::with_counter(key, nonce, 0)
+    }
+
+    /// Starts the key stream at block `counter` instead of 0.
+    fn with_counter(key : &[u8], nonce : &[u8], counter : u32) -> ChaCha20 {
+        assert_eq!(key.len(), 32);
+        assert_eq!(nonce.len(), 12);
+
+        let mut state = [0u32; 16];
+        state[..4].copy_from_slice(&SIGMA);
+
+        for i in 0..8 {
+            state[4 + i] = LittleEndian::read_u32(&key[i * 4..]);
+        }
+
+        state[12] = counter;
+        for i in 0..3 {
+            state[13 + i] = LittleEndian::read_u32(&nonce[i * 4..]);
+        }
+
+        ChaCha20 {
+            state,
+            block: [0u8; 64],
+            pos: 64,
+            exhausted: false,
+        }
+    }
+
+    fn next_block(&mut self) -> Result<(), Error> {
+        if self.exhausted {
+            return Err(Error::new("chacha20 key stream exhausted"));
+        }
+
+        let mut x = self.state;
+
+        for _ in 0..10 {
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 1, 5, 9, 13);
+            quarter_round(&mut x, 2, 6, 10, 14);
+            quarter_round(&mut x, 3, 7, 11, 15);
+
+            quarter_round(&mut x, 0, 5, 10, 15);
+            quarter_round(&mut x, 1, 6, 11, 12);
+            quarter_round(&mut x, 2, 7, 8, 13);
+            quarter_round(&mut x, 3, 4, 9, 14);
+        }
+
+        for (i, word) in x.iter().enumerate() {
+            let word = word.wrapping_add(self.state[i]);
+            LittleEndian::write_u32(&mut self.block[i * 4..], word);
+        }
+
+        match self.state[12].checked_add(1) {
+            Some(x) => self.state[12] = x,
+            None    => self.exhausted = true,
+        }
+
+        self.pos = 0;
+        Ok(())
+    }
+
+    /// XORs the next bytes of the key stream into `data`. Fails once the
+    /// 256 GiB of key stream a nonce allows have been used.
+    pub fn apply(&mut self, data : &mut [u8]) -> Result<(), Error> {
+        for byte in data.iter_mut() {
+            if 64 == self.pos {
+                self.next_block()?;
+            }
+
+            *byte ^= self.block[self.pos];
+            self.pos += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex::FromHex;
+
+    /// RFC 7539, appendix A.1, test vector 1.
+    #[test]
+    fn rfc7539_zero_key() {
+        let expected = Vec::from_hex("76b8e0ada0f13d90405d6ae55386bd28\
+                                      bdd219b8a08ded1aa836efcc8b770dc7\
+                                      da41597c5157488d7724e03fb8d84a37\
+                                      6a43b8f41518a11cc387b669b2ee6586").unwrap();
+
+        let mut data = [0u8; 64];
+        ChaCha20::new(&[0u8; 32], &[0u8; 12]).apply(&mut data).unwrap();
+        assert_eq!(&data[..], &expected[..]);
+    }
+
+    /// RFC 7539, section 2.4.2.
+    #[test]
+    fn rfc7539_sunscreen() {
+        let key : Vec<u8> = (0..32).collect();
+        let nonce = Vec::from_hex("000000000000004a00000000").unwrap();
+        let expected = Vec::from_hex("6e2e359a2568f98041ba0728dd0d6981\
+                                      e97e7aec1d4360c20a27afccfd9fae0b\
+                                      f91b65c5524733ab8f593dabcd62b357\
+                                      1639d624e65152ab8f530c359f0861d8\
+                                      07ca0dbf500d6a6156a38e088a22b65e\
+                                      52bc514d16ccf806818ce91ab7793736\
+                                      5af90bbf74a35be6b40b8eedf2785e42\
+                                      874d").unwrap();
+
+        let mut data = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+                         only one tip for the future, sunscreen would be it.".to_vec();
+
+        // Applying the key stream in uneven pieces gives the same result.
+        let mut cipher = ChaCha20::with_counter(&key, &nonce, 1);
+        let (head, tail) = data.split_at_mut(7);
+        cipher.apply(head).unwrap();
+        cipher.apply(tail).unwrap();
+
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn counter_exhausted() {
+        let mut cipher = ChaCha20::with_counter(&[0u8; 32], &[0u8; 12], u32::MAX);
+
+        let mut data = [0u8; 64];
+        cipher.apply(&mut data).unwrap();
+        assert!(cipher.apply(&mut data[..1]).is_err());
+        assert!(cipher.apply(&mut data[..1]).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use ::{Binary, Compression, Database, Error, InnerStreamCipher, Kdf, OuterCipher, Version};
+use meta::META_ORDER;
+use times;
+use xml;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use base64;
+use elementtree::Element;
+use flate2;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use openssl::rand;
+
+fn random(len : usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![0u8; len];
+    rand::rand_bytes(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn is_true(e : &Element, attr : &str) -> bool {
+    e.get_attr(attr).and_then(xml::parse_bool).unwrap_or(false)
+}
+
+/// Rewrites every timestamp in the document in the encoding `version`
+/// uses. Timestamps are the leaf elements named `...Time` or `...Changed`.
+pub fn convert_times(e : &mut Element, version : &Version) {
+    let is_time = {
+        let name = e.tag().name();
+        (name.ends_with("Time") || name.ends_with("Changed")) && 0 == e.child_count()
+    };
+
+    if is_time {
+        if let Ok(t) = times::parse_time(e.text()) {
+            e.set_text(times::format_time(&t, version));
+        }
+    }
+
+    for child in e.children_mut() {
+        convert_times(child, version);
+    }
+}
+
+fn remove_children(e : &mut Element, tag : &str) {
+    while let Some(idx) = e.children().position(|x| x.tag().name() == tag) {
+        e.remove_child(idx);
+    }
+}
+
+/// Removes the elements `version` doesn't have: custom data on groups and
+/// entries before KDBX 4, and `QualityCheck`, `PreviousParentGroup`, group
+/// tags and the modification times of custom data items before KDBX 4.1.
+fn strip_newer(e : &mut Element, version : &Version) {
+    let tag = e.tag().name().to_owned();
+
+    if !version.at_least(4, 0) && (tag == "Entry" || tag == "Group") {
+        remove_children(e, "CustomData");
+    }
+
+    if !version.at_least(4, 1) {
+        match &tag[..] {
+            "Entry" => {
+                remove_children(e, "QualityCheck");
+                remove_children(e, "PreviousParentGroup");
+            },
+            "Group" => {
+                remove_children(e, "Tags");
+                remove_children(e, "PreviousParentGroup");
+            },
+            "CustomData" => for item in e.find_all_mut("Item") {
+                remove_children(item, "LastModificationTime");
+            },
+            _ => (),
+        }
+    }
+
+    for child in e.children_mut() {
+        strip_newer(child, version);
+    }
+}
+
+/// Points the attachments of entries, including their history, at new
+/// binary ids.
+fn remap_refs(e : &mut Element, ids : &HashMap<String, usize>) {
+    if e.tag().name() == "Binary" {
+        if let Some(value) = e.find_mut("Value") {
+            let id = value.get_attr("Ref").and_then(|x| ids.get(x.trim())).cloned();
+            if let Some(id) = id {
+                value.set_attr("Ref", id.to_string());
+            }
+        }
+    }
+
+    for child in e.children_mut() {
+        remap_refs(child, ids);
+    }
+}
+
+/// Moves the binaries in `<Meta><Binaries>`, where KDBX 3.1 keeps them,
+/// to the end of `binaries`, the list KDBX 4 stores in the inner header.
+/// The attachments pointing at them are renumbered to match.
+pub fn take_meta_binaries(doc : &mut Element, binaries : &mut Vec<Binary>)
+                          -> Result<(), Error> {
+    let pool = match doc.find_mut("Meta") {
+        None        => return Ok(()),
+        Some(meta)  => match meta.children().position(|x| x.tag().name() == "Binaries") {
+            None        => return Ok(()),
+            Some(idx)   => meta.remove_child(idx).unwrap(),
+        },
+    };
+
+    let mut ids = HashMap::new();
+    for e in pool.find_all("Binary") {
+        let mut data = base64::decode(e.text().trim())
+                       .map_err(|_| Error::new("malformed binary"))?;
+
+        if is_true(e, "Compressed") {
+            let mut plain = Vec::new();
+            GzDecoder::new(&data[..])?.read_to_end(&mut plain)?;
+            data = plain;
+        }
+
+        if let Some(id) = e.get_attr("ID") {
+            ids.insert(id.trim().to_owned(), binaries.len());
+        }
+
+        binaries.push(Binary {
+            protected: is_true(e, "Protected"),
+            data,
+        });
+    }
+
+    if let Some(root) = doc.find_mut("Root") {
+        remap_refs(root, &ids);
+    }
+
+    Ok(())
+}
+
+/// Replaces `<Meta><Binaries>` with `binaries`, numbered in order. Like
+/// KeePass, protected binaries are left uncompressed, since the inner stream
+/// encrypts them, and the others are compressed if `compressed` is set.
+pub fn put_meta_binaries(doc : &mut Element, binaries : &[Binary], compressed : bool)
+                         -> Result<(), Error> {
+    let mut elems = Vec::new();
+
+    for (id, binary) in binaries.iter().enumerate() {
+        let mut e = Element::new("Binary");
+        e.set_attr("ID", id.to_string());
+
+        let data = if binary.protected {
+            e.set_attr("Protected", xml::format_bool(true));
+            binary.data.clone()
+        } else if compressed {
+            e.set_attr("Compressed", xml::format_bool(true));
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::Default);
+            encoder.write_all(&binary.data)?;
+            encoder.finish()?
+        } else {
+            binary.data.clone()
+        };
+
+        e.set_text(base64::encode(&data));
+        elems.push(e);
+    }
+
+    let meta = doc.find_mut("Meta")
+                  .ok_or_else(|| Error::new("missing meta"))?;
+    let pool = xml::ordered_child(meta, "Binaries", META_ORDER);
+    while pool.child_count() > 0 {
+        pool.remove_child(0);
+    }
+    for e in elems {
+        pool.append_child(e);
+    }
+
+    Ok(())
+}
+
+/// Moves the binaries in `<Meta><Binaries>` into the inner header used by
+/// KDBX 4.
+pub fn binaries_to_header(db : &mut Database) -> Result<(), Error> {
+    take_meta_binaries(&mut db.xml_doc, &mut db.binaries)
+}
+
+/// Moves the binaries from the inner header into `<Meta><Binaries>`.
+pub fn binaries_to_meta(db : &mut Database) -> Result<(), Error> {
+    if db.binaries.is_empty() {
+        return Ok(());
+    }
+
+    let compressed = Compression::GZip == db.compression;
+    put_meta_binaries(&mut db.xml_doc, &db.binaries, compressed)?;
+    db.binaries.clear();
+    Ok(())
+}
+
+impl Database {
+    /// Changes the file format the database is saved in. Going between
+    /// KDBX 3.1 and 4 moves the binaries between `<Meta>` and the inner
+    /// header, converts every timestamp, and switches the inner stream to
+    /// the cipher KeePass uses for that format. Going to an older version
+    /// drops the elements it doesn't support.
+    ///
+    /// KDBX 3.1 only supports the AES KDF and cipher, so those have to be
+    /// chosen before going back to it.
+    pub fn set_version(&mut self, version : Version) -> Result<(), Error> {
+        match (version.major, version.minor) {
+            (3, 1) | (4, 0) | (4, 1)    => (),
+            _                           => return Err(Error::new("unsupported version")),
+        }
+
+        if 3 == version.major {
+            if Kdf::Aes != self.kdf {
+                return Err(Error::new("kdbx 3.1 only supports the aes kdf"));
+            }
+
+            if OuterCipher::Aes128 != self.outer_cipher {
+                return Err(Error::new("kdbx 3.1 only supports the aes cipher"));
+            }
+        }
+
+        let (cipher, key_len) = match (self.version.major, version.major) {
+            (3, 4) => {
+                binaries_to_header(self)?;
+                self.stream_start_bytes = vec![];
+                (InnerStreamCipher::ChaCha20, 64)
+            },
+            (4, 3) => {
+                binaries_to_meta(self)?;
+                self.stream_start_bytes = random(32)?;
+                (InnerStreamCipher::Salsa20, 32)
+            },
+            _ => {
+                strip_newer(&mut self.xml_doc, &version);
+                self.xml_version = version;
+                self.version = version;
+                return Ok(());
+            },
+        };
+
+        if InnerStreamCipher::None != self.inner_stream_cipher {
+            self.inner_stream_cipher = cipher;
+            self.inner_stream_key = Some(random(key_len)?);
+        }
+
+        strip_newer(&mut self.xml_doc, &version);
+        convert_times(&mut self.xml_doc, &version);
+        self.xml_version = version;
+        self.version = version;
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use ::{Error, Version};
+use times;
+use xml;
+
+use chrono::{DateTime, Utc};
+use elementtree::Element;
+
+/// A single `<Item>` from a `<CustomData>` dictionary.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CustomDataItem {
+    pub key                     : String,
+    pub value                   : String,
+
+    /// Only stored by KDBX 4.1 and later.
+    pub last_modification_time  : Option<DateTime<Utc>>,
+}
+
+/// A `<CustomData>` dictionary, as used by plugins to store their settings
+/// on the database, groups and entries. Items keep their original order.
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CustomData {
+    items : Vec<CustomDataItem>,
+}
+
+impl CustomData {
+    pub fn new() -> CustomData {
+        CustomData {
+            items: vec![],
+        }
+    }
+
+    pub fn from_xml(e : &Element) -> Result<CustomData, Error> {
+        let mut items = Vec::new();
+
+        for item in e.find_all("Item") {
+            let key = xml::child_text(item, "Key")
+                      .ok_or_else(|| Error::new("missing custom data key"))?;
+
+            let modified = match xml::child_text(item, "LastModificationTime") {
+                None    => None,
+                Some(x) => Some(times::parse_time(x)?),
+            };
+
+            items.push(CustomDataItem {
+                key: key.to_owned(),
+                value: xml::child_text(item, "Value").unwrap_or("").to_owned(),
+                last_modification_time: modified,
+            });
+        }
+
+        Ok(CustomData {
+            items,
+        })
+    }
+
+    /// Replaces the items of a `<CustomData>` element. Modification times are
+    /// only written for KDBX 4.1 and later, which is where they were added.
+    pub fn write_xml(&self, e : &mut Element, version : &Version) {
+        while let Some(idx) = e.children().position(|x| x.tag().name() == "Item") {
+            e.remove_child(idx);
+        }
+
+        let with_times = version.at_least(4, 1);
+
+        for item in &self.items {
+            let child = e.append_new_child("Item");
+            xml::set_child_text(child, "Key", item.key.clone());
+            xml::set_child_text(child, "Value", item.value.clone());
+
+            if let (true, Some(t)) = (with_times, item.last_modification_time) {
+                xml::set_child_text(child, "LastModificationTime",
+                                    times::format_time(&t, version));
+            }
+        }
+    }
+
+    pub fn get(&self, key : &str) -> Option<&str> {
+        self.get_item(key).map(|x| &x.value[..])
+    }
+
+    pub fn get_item(&self, key : &str) -> Option<&CustomDataItem> {
+        self.items.iter().find(|x| x.key == key)
+    }
+
+    /// Sets the value for `key`, stamping the item's modification time if the
+    /// value changed.
+    pub fn set<K, V>(&mut self, key : K, value : V)
+        where K : Into<String>, V : Into<String>
+    {
+        let key = key.into();
+        let value = value.into();
+
+        if let Some(item) = self.items.iter_mut().find(|x| x.key == key) {
+            if item.value != value {
+                item.value = value;
+                item.last_modification_time = Some(Utc::now());
+            }
+            return;
+        }
+
+        self.items.push(CustomDataItem {
+            key,
+            value,
+            last_modification_time: Some(Utc::now()),
+        });
+    }
+
+    pub fn remove(&mut self, key : &str) -> Option<String> {
+        self.items.iter()
+                  .position(|x| x.key == key)
+                  .map(|x| self.items.remove(x).value)
+    }
+
+    pub fn items(&self) -> &[CustomDataItem] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
@@ -0,0 +1,217 @@
+use ::{AutoType, Compression, CustomData, Database, DeletedObject, Entry, Error,
+        Group, InnerStreamCipher, Kdf, Meta, OuterCipher, Times, Uuid,
+        VariantDictionary, Version};
+
+/// Whether snapshots include the plaintext of protected values, such as
+/// passwords. They are redacted unless explicitly asked for.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Default)]
+pub enum ProtectedValues {
+    #[default]
+    Redact,
+    Include,
+}
+
+/// A string field of an entry. `value` is `None` when the field is
+/// protected and its value was redacted.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StringField {
+    pub key         : String,
+    pub value       : Option<String>,
+    pub protected   : bool,
+}
+
+/// An owned copy of an entry, detached from the database.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryData {
+    pub uuid                : Uuid,
+    pub fields              : Vec<StringField>,
+    pub tags                : Vec<String>,
+    pub foreground_color    : Option<String>,
+    pub background_color    : Option<String>,
+    pub override_url        : Option<String>,
+    pub times               : Times,
+    pub auto_type           : AutoType,
+    pub custom_data         : CustomData,
+    pub history             : Vec<EntryData>,
+}
+
+impl EntryData {
+    pub fn new(entry : &Entry, protected : ProtectedValues) -> Result<EntryData, Error> {
+        let fields = entry.field_names()
+            .into_iter()
+            .map(|key| {
+                let is_protected = entry.is_protected(key);
+                let value = match (is_protected, protected) {
+                    (true, ProtectedValues::Redact) => None,
+                    _ => entry.field(key).map(|x| x.to_owned()),
+                };
+
+                StringField {
+                    key: key.to_owned(),
+                    value,
+                    protected: is_protected,
+                }
+            })
+            .collect();
+
+        let history = entry.history()
+                           .iter()
+                           .map(|x| EntryData::new(x, protected))
+                           .collect::<Result<_, _>>()?;
+
+        Ok(EntryData {
+            uuid: entry.uuid().ok_or_else(|| Error::new("missing entry uuid"))?,
+            fields,
+            tags: entry.tags(),
+            foreground_color: entry.foreground_color().map(|x| x.to_owned()),
+            background_color: entry.background_color().map(|x| x.to_owned()),
+            override_url: entry.override_url().map(|x| x.to_owned()),
+            times: entry.times()?,
+            auto_type: entry.auto_type()?,
+            custom_data: entry.custom_data()?,
+            history,
+        })
+    }
+
+    pub fn field(&self, key : &str) -> Option<&StringField> {
+        self.fields.iter().find(|x| x.key == key)
+    }
+}
+
+/// An owned copy of a group and everything inside it.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GroupData {
+    pub uuid                        : Uuid,
+    pub name                        : String,
+    pub notes                       : String,
+    pub times                       : Times,
+    pub is_expanded                 : bool,
+    pub default_auto_type_sequence  : Option<String>,
+    pub enable_auto_type            : Option<bool>,
+    pub enable_searching            : Option<bool>,
+    pub custom_data                 : CustomData,
+    pub entries                     : Vec<EntryData>,
+    pub groups                      : Vec<GroupData>,
+}
+
+impl GroupData {
+    pub fn new(group : &Group, protected : ProtectedValues) -> Result<GroupData, Error> {
+        let entries = group.entries()
+                           .iter()
+                           .map(|x| EntryData::new(x, protected))
+                           .collect::<Result<_, _>>()?;
+
+        let groups = group.groups()
+                          .iter()
+                          .map(|x| GroupData::new(x, protected))
+                          .collect::<Result<_, _>>()?;
+
+        Ok(GroupData {
+            uuid: group.uuid().ok_or_else(|| Error::new("missing group uuid"))?,
+            name: group.name().unwrap_or("").to_owned(),
+            notes: group.notes().unwrap_or("").to_owned(),
+            times: group.times()?,
+            is_expanded: group.is_expanded(),
+            default_auto_type_sequence: group.default_auto_type_sequence()
+                                             .map(|x| x.to_owned()),
+            enable_auto_type: group.enable_auto_type(),
+            enable_searching: group.enable_searching(),
+            custom_data: group.custom_data()?,
+            entries,
+            groups,
+        })
+    }
+}
+
+/// An owned copy of the database settings from `<Meta>`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MetaData {
+    pub generator               : Option<String>,
+    pub database_name           : Option<String>,
+    pub database_description    : Option<String>,
+    pub default_user_name       : Option<String>,
+    pub recycle_bin_enabled     : bool,
+    pub recycle_bin_uuid        : Option<Uuid>,
+    pub entry_templates_group   : Option<Uuid>,
+    pub history_max_items       : Option<usize>,
+    pub custom_data             : CustomData,
+}
+
+impl MetaData {
+    pub fn new(meta : &Meta) -> Result<MetaData, Error> {
+        Ok(MetaData {
+            generator: meta.generator().map(|x| x.to_owned()),
+            database_name: meta.database_name().map(|x| x.to_owned()),
+            database_description: meta.database_description().map(|x| x.to_owned()),
+            default_user_name: meta.default_user_name().map(|x| x.to_owned()),
+            recycle_bin_enabled: meta.recycle_bin_enabled(),
+            recycle_bin_uuid: meta.recycle_bin_uuid(),
+            entry_templates_group: meta.entry_templates_group(),
+            history_max_items: meta.history_max_items(),
+            custom_data: meta.custom_data()?,
+        })
+    }
+}
+
+/// The outer header fields of a database. The protected stream key and the
+/// stream start bytes are left out, since they help decrypt the payload.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DatabaseHeader {
+    pub version             : Version,
+    pub compression         : Compression,
+    pub outer_cipher        : OuterCipher,
+    pub master_seed         : Vec<u8>,
+    pub kdf                 : Kdf,
+    pub transform_seed      : Vec<u8>,
+    pub transform_rounds    : u64,
+    pub encryption_iv       : Vec<u8>,
+    pub inner_stream_cipher : InnerStreamCipher,
+    pub other_headers       : Vec<(u8, Vec<u8>)>,
+    pub public_custom_data  : VariantDictionary,
+}
+
+/// An owned copy of a whole database.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DatabaseData {
+    pub header          : DatabaseHeader,
+    pub meta            : MetaData,
+    pub root            : GroupData,
+    pub deleted_objects : Vec<DeletedObject>,
+}
+
+impl Database {
+    pub fn header(&self) -> DatabaseHeader {
+        DatabaseHeader {
+            version: self.version,
+            compression: self.compression,
+            outer_cipher: self.outer_cipher,
+            master_seed: self.master_seed.clone(),
+            kdf: self.kdf,
+            transform_seed: self.transform_seed.clone(),
+            transform_rounds: self.transform_rounds,
+            encryption_iv: self.encryption_iv.clone(),
+            inner_stream_cipher: self.inner_stream_cipher,
+            other_headers: self.other_headers.clone(),
+            public_custom_data: self.public_custom_data.clone(),
+        }
+    }
+
+    /// Copies the database into plain structs, for example to serialize it.
+    pub fn to_data(&self, protected : ProtectedValues) -> Result<DatabaseData, Error> {
+        let meta = self.meta().ok_or_else(|| Error::new("missing meta"))?;
+        let root = self.root_group().ok_or_else(|| Error::new("missing root group"))?;
+
+        Ok(DatabaseData {
+            header: self.header(),
+            meta: MetaData::new(&meta)?,
+            root: GroupData::new(&root, protected)?,
+            deleted_objects: self.deleted_objects()?,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use ::{Database, Error, Uuid, Version};
+use times;
+use xml;
+
+use chrono::{DateTime, Duration, Utc};
+use elementtree::Element;
+
+/// A tombstone from `<Root><DeletedObjects>`, recording that an item was
+/// permanently deleted so that synchronization doesn't bring it back.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeletedObject {
+    pub uuid            : Uuid,
+    pub deletion_time   : DateTime<Utc>,
+}
+
+impl DeletedObject {
+    pub fn from_xml(e : &Element) -> Result<DeletedObject, Error> {
+        let uuid = xml::item_uuid(e)
+                   .ok_or_else(|| Error::new("malformed deleted object uuid"))?;
+
+        let deletion_time = xml::child_text(e, "DeletionTime")
+                            .ok_or_else(|| Error::new("missing deletion time"))
+                            .and_then(times::parse_time)?;
+
+        Ok(DeletedObject {
+            uuid,
+            deletion_time,
+        })
+    }
+
+    pub fn to_xml(&self, version : &Version) -> Element {
+        let mut e = Element::new("DeletedObject");
+        xml::set_child_text(&mut e, "UUID", self.uuid.to_base64());
+        xml::set_child_text(&mut e, "DeletionTime",
+                            times::format_time(&self.deletion_time, version));
+        e
+    }
+}
+
+/// Collects the UUIDs of a group and everything nested inside it.
+fn collect_uuids(group : &Element, out : &mut Vec<Uuid>) {
+    out.extend(xml::item_uuid(group));
+
+    for entry in group.find_all("Entry") {
+        out.extend(xml::item_uuid(entry));
+    }
+
+    for child in group.find_all("Group") {
+        collect_uuids(child, out);
+    }
+}
+
+impl Database {
+    fn deleted_objects_elem(&mut self) -> Result<&mut Element, Error> {
+        let root = self.xml_doc.find_mut("Root")
+                               .ok_or_else(|| Error::new("missing root"))?;
+
+        if root.find("DeletedObjects").is_none() {
+            root.append_new_child("DeletedObjects");
+        }
+
+        Ok(root.find_mut("DeletedObjects").unwrap())
+    }
+
+    fn record_deletions(&mut self, uuids : Vec<Uuid>) -> Result<(), Error> {
+        let now = Utc::now();
+        let version = self.version;
+
+        let deleted = self.deleted_objects_elem()?;
+        for uuid in uuids {
+            let obj = DeletedObject {
+                uuid,
+                deletion_time: now,
+            };
+            deleted.append_child(obj.to_xml(&version));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the tombstones of permanently deleted groups and entries.
+    pub fn deleted_objects(&self) -> Result<Vec<DeletedObject>, Error> {
+        match self.xml_doc.find("Root").and_then(|x| x.find("DeletedObjects")) {
+            None    => Ok(vec![]),
+            Some(x) => x.find_all("DeletedObject")
+                        .map(DeletedObject::from_xml)
+                        .collect(),
+        }
+    }
+
+    /// Permanently removes an entry and records a tombstone for it.
+    pub fn remove_entry(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        self.root_group_elem_mut()
+            .map(|x| xml::take_item(x, "Entry", uuid))?
+            .ok_or_else(|| Error::new("entry not found"))?;
+
+        self.record_deletions(vec![*uuid])
+    }
+
+    /// Permanently removes a group, along with everything inside it, and
+    /// records a tombstone for each removed group and entry.
+    pub fn remove_group(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        let group = {
+            let root = self.root_group_elem_mut()?;
+
+            if xml::item_uuid(root).as_ref() == Some(uuid) {
+                return Err(Error::new("cannot remove the root group"));
+            }
+
+            xml::take_item(root, "Group", uuid)
+                .ok_or_else(|| Error::new("group not found"))?
+        };
+
+        let mut uuids = Vec::new();
+        collect_uuids(&group, &mut uuids);
+        self.record_deletions(uuids)
+    }
+
+    /// Removes tombstones that are older than `max_age`, returning how many
+    /// were removed.
+    pub fn prune_deleted_objects(&mut self, max_age : Duration) -> Result<usize, Error> {
+        let cutoff = Utc::now() - max_age;
+        let deleted = match self.xml_doc.find_mut("Root")
+                                        .and_then(|x| x.find_mut("DeletedObjects")) {
+            None    => return Ok(0),
+            Some(x) => x,
+        };
+
+        let mut pruned = 0;
+        let mut idx = 0;
+        while idx < deleted.child_count() {
+            let expired = {
+                let child = deleted.get_child(idx).unwrap();
+                child.tag().name() == "DeletedObject"
+                    && DeletedObject::from_xml(child)
+                       .map(|x| x.deletion_time < cutoff)
+                       .unwrap_or(false)
+            };
+
+            if expired {
+                deleted.remove_child(idx);
+                pruned += 1;
+            } else {
+                idx += 1;
+            }
+        }
+
+        Ok(pruned)
+    }
+}
@@ -0,0 +1,167 @@
+use ::{Database, EntryMut, Error, GroupMut, Times, Uuid, Version};
+use entry::{self, ENTRY_ORDER};
+use group::{self, GROUP_ORDER};
+use xml;
+
+use chrono::Utc;
+use elementtree::Element;
+
+/// Stamps the `<Times>` of a group or entry as used, and modified if
+/// `modified` is set.
+fn touch(e : &mut Element, order : &[&str], modified : bool,
+         version : &Version) -> Result<(), Error> {
+    let now = Utc::now();
+    let mut times = match e.find("Times") {
+        None    => Times::new(now),
+        Some(x) => Times::from_xml(x)?,
+    };
+
+    times.touch(now, modified);
+    times.write_xml(xml::ordered_child(e, "Times", order), version);
+    Ok(())
+}
+
+/// Copies an entry without its `<History>`.
+fn without_history(e : &Element) -> Element {
+    let mut copy = e.clone();
+    while let Some(idx) = copy.children().position(|x| x.tag().name() == "History") {
+        copy.remove_child(idx);
+    }
+    copy
+}
+
+fn serialize(e : &Element) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    e.to_writer(&mut out)?;
+    Ok(out)
+}
+
+/// Appends `snapshot` to the entry's history, then removes the oldest items
+/// until there are at most `max_items` and they take up at most `max_size`
+/// bytes. Like KeePass, the size is an estimate; here it is the length of
+/// the serialized XML.
+fn add_history(e : &mut Element, snapshot : Element, max_items : Option<usize>,
+               max_size : Option<u64>) -> Result<(), Error> {
+    let history = xml::ordered_child(e, "History", ENTRY_ORDER);
+    history.append_child(snapshot);
+
+    loop {
+        let mut count = 0;
+        let mut size = 0;
+        for item in history.find_all("Entry") {
+            count += 1;
+            size += serialize(item)?.len() as u64;
+        }
+
+        let too_many = max_items.map(|x| count > x).unwrap_or(false);
+        let too_big = max_size.map(|x| size > x).unwrap_or(false);
+        if 0 == count || !(too_many || too_big) {
+            return Ok(());
+        }
+
+        let idx = history.children()
+                         .position(|x| x.tag().name() == "Entry")
+                         .unwrap();
+        history.remove_child(idx);
+    }
+}
+
+impl Database {
+    fn item_elem_mut(&mut self, tag : &str, uuid : &Uuid) -> Result<&mut Element, Error> {
+        let not_found = match tag {
+            "Entry" => "entry not found",
+            _       => "group not found",
+        };
+
+        self.root_group_elem_mut()
+            .ok()
+            .and_then(|x| xml::find_item_mut(x, tag, uuid))
+            .ok_or_else(|| Error::new(not_found))
+    }
+
+    /// Creates an empty group with a fresh UUID at the end of `parent`,
+    /// returning its UUID.
+    pub fn create_group(&mut self, parent : &Uuid, name : &str) -> Result<Uuid, Error> {
+        let uuid = Uuid::new_v4()?;
+        let group = group::new_element(&uuid, name, &self.version);
+
+        xml::append_item(self.item_elem_mut("Group", parent)?, group);
+        Ok(uuid)
+    }
+
+    /// Creates an entry with empty standard fields and a fresh UUID after
+    /// the other entries in `parent`, returning its UUID.
+    pub fn create_entry(&mut self, parent : &Uuid) -> Result<Uuid, Error> {
+        let uuid = Uuid::new_v4()?;
+        let entry = entry::new_element(&uuid, &self.version);
+
+        xml::append_item(self.item_elem_mut("Group", parent)?, entry);
+        Ok(uuid)
+    }
+
+    /// Edits an entry the way KeePass does: if the edit changed anything, the
+    /// previous version is copied into the entry's history, which is then
+    /// trimmed to the database's `HistoryMaxItems` and `HistoryMaxSize`, and
+    /// the entry's modification time and usage count are updated.
+    ///
+    /// If updating the times fails, the entry is left as it was.
+    pub fn update_entry<F>(&mut self, uuid : &Uuid, f : F) -> Result<(), Error>
+        where F : FnOnce(&mut EntryMut)
+    {
+        let (max_items, max_size) = match self.meta() {
+            None    => (None, None),
+            Some(x) => (x.history_max_items(), x.history_max_size()),
+        };
+        let version = self.version;
+        let elem = self.item_elem_mut("Entry", uuid)?;
+
+        let before = elem.clone();
+        let snapshot = without_history(elem);
+
+        f(&mut EntryMut::new(elem, version));
+
+        if serialize(&without_history(elem))? == serialize(&snapshot)? {
+            return Ok(());
+        }
+
+        let result = add_history(elem, snapshot, max_items, max_size)
+                         .and_then(|_| touch(elem, ENTRY_ORDER, true, &version));
+        if result.is_err() {
+            *elem = before;
+        }
+        result
+    }
+
+    /// Edits a group, then updates its modification time and usage count if
+    /// the edit changed anything.
+    ///
+    /// If updating the times fails, the group is left as it was.
+    pub fn update_group<F>(&mut self, uuid : &Uuid, f : F) -> Result<(), Error>
+        where F : FnOnce(&mut GroupMut)
+    {
+        let version = self.version;
+        let elem = self.item_elem_mut("Group", uuid)?;
+
+        let before = elem.clone();
+
+        f(&mut GroupMut::new(elem, version));
+
+        if serialize(elem)? == serialize(&before)? {
+            return Ok(());
+        }
+
+        let result = touch(elem, GROUP_ORDER, true, &version);
+        if result.is_err() {
+            *elem = before;
+        }
+        result
+    }
+
+    /// Records that an entry was used, such as by copying its password,
+    /// which updates its access time and usage count.
+    pub fn touch_entry(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        let version = self.version;
+        let elem = self.item_elem_mut("Entry", uuid)?;
+        touch(elem, ENTRY_ORDER, false, &version)
+    }
+}
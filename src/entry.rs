@@ -0,0 +1,379 @@
+use ::{AutoType, CustomData, Database, Error, Times, Uuid, Version};
+use std::collections::BTreeMap;
+use xml;
+
+use chrono::Utc;
+use elementtree::Element;
+
+/// The children of an `<Entry>`, in the order KeePass writes them.
+pub const ENTRY_ORDER : &[&str] = &[
+    "UUID", "IconID", "CustomIconUUID", "ForegroundColor", "BackgroundColor",
+    "OverrideURL", "QualityCheck", "Tags", "PreviousParentGroup", "Times",
+    "String", "Binary", "AutoType", "CustomData", "History",
+];
+
+/// The string fields KeePass gives every new entry.
+const STANDARD_FIELDS : &[&str] = &[
+    "Title", "UserName", "Password", "URL", "Notes",
+];
+
+/// Builds an empty entry the way KeePass creates one.
+pub fn new_element(uuid : &Uuid, version : &Version) -> Element {
+    let mut e = Element::new("Entry");
+    xml::set_child_text(&mut e, "UUID", uuid.to_base64());
+    xml::set_child_text(&mut e, "IconID", "0");
+    xml::set_child_text(&mut e, "ForegroundColor", "");
+    xml::set_child_text(&mut e, "BackgroundColor", "");
+    xml::set_child_text(&mut e, "OverrideURL", "");
+    xml::set_child_text(&mut e, "Tags", "");
+    Times::new(Utc::now()).write_xml(e.append_new_child("Times"), version);
+
+    {
+        let mut entry = EntryMut::new(&mut e, *version);
+        for key in STANDARD_FIELDS {
+            entry.set_field(key, "");
+        }
+    }
+
+    AutoType::new().write_xml(e.append_new_child("AutoType"));
+    e.append_new_child("History");
+    e
+}
+
+/// Splits a `<Tags>` value, which KeePass and KeePassXC delimit with either
+/// `;` or `,`.
+fn split_tags(s : &str) -> Vec<String> {
+    let mut tags : Vec<String> = Vec::new();
+
+    for tag in s.split(&[';', ','][..]).map(|x| x.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|x| tags_equal(x, tag)) {
+            tags.push(tag.to_owned());
+        }
+    }
+
+    tags
+}
+
+/// Tags are compared case insensitively, as KeePass does.
+fn tags_equal(a : &str, b : &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+fn non_empty(s : &str) -> Option<&str> {
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s) }
+}
+
+/// A read-only view of an `<Entry>` in the database.
+#[derive(Debug,Clone,Copy)]
+pub struct Entry<'a> {
+    elem : &'a Element,
+}
+
+impl<'a> Entry<'a> {
+    pub fn new(elem : &'a Element) -> Entry<'a> {
+        Entry {
+            elem,
+        }
+    }
+
+    pub fn uuid(&self) -> Option<Uuid> {
+        xml::item_uuid(self.elem)
+    }
+
+    /// Returns the value of the string field named `key`, such as `Title` or
+    /// `UserName`.
+    pub fn field(&self, key : &str) -> Option<&'a str> {
+        self.elem.find_all("String")
+                 .find(|x| xml::child_text(x, "Key") == Some(key))
+                 .and_then(|x| xml::child_text(x, "Value"))
+    }
+
+    pub fn title(&self) -> Option<&'a str> {
+        self.field("Title")
+    }
+
+    /// Returns the names of the entry's string fields, in document order.
+    pub fn field_names(&self) -> Vec<&'a str> {
+        self.elem.find_all("String")
+                 .filter_map(|x| xml::child_text(x, "Key"))
+                 .collect()
+    }
+
+    /// Checks whether the string field named `key` is stored encrypted with
+    /// the inner stream cipher.
+    pub fn is_protected(&self, key : &str) -> bool {
+        self.elem.find_all("String")
+                 .find(|x| xml::child_text(x, "Key") == Some(key))
+                 .and_then(|x| x.find("Value"))
+                 .and_then(|x| x.get_attr("Protected"))
+                 .and_then(xml::parse_bool)
+                 .unwrap_or(false)
+    }
+
+    /// Returns the entry's tags, without duplicates.
+    pub fn tags(&self) -> Vec<String> {
+        xml::child_text(self.elem, "Tags").map(split_tags).unwrap_or(vec![])
+    }
+
+    pub fn has_tag(&self, tag : &str) -> bool {
+        self.tags().iter().any(|x| tags_equal(x, tag))
+    }
+
+    /// Returns the foreground color, such as `#FF0000`, if one is set.
+    pub fn foreground_color(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "ForegroundColor").and_then(non_empty)
+    }
+
+    /// Returns the background color, such as `#FF0000`, if one is set.
+    pub fn background_color(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "BackgroundColor").and_then(non_empty)
+    }
+
+    /// Returns the URL override, used instead of the `URL` field when the
+    /// entry's URL is opened.
+    pub fn override_url(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "OverrideURL").and_then(non_empty)
+    }
+
+    /// Returns whether the password quality estimation is enabled for the
+    /// entry. Only stored by KDBX 4.1 and later, and enabled when missing.
+    pub fn quality_check(&self) -> bool {
+        xml::child_text(self.elem, "QualityCheck")
+            .and_then(xml::parse_bool)
+            .unwrap_or(true)
+    }
+
+    /// Returns the UUID of the group the entry was in before it was last
+    /// moved. Only stored by KDBX 4.1 and later.
+    pub fn previous_parent_group(&self) -> Option<Uuid> {
+        xml::child_text(self.elem, "PreviousParentGroup")
+            .and_then(|x| Uuid::from_base64(x).ok())
+    }
+
+    pub fn times(&self) -> Result<Times, Error> {
+        self.elem.find("Times")
+                 .ok_or_else(|| Error::new("missing times"))
+                 .and_then(Times::from_xml)
+    }
+
+    pub fn auto_type(&self) -> Result<AutoType, Error> {
+        match self.elem.find("AutoType") {
+            None    => Ok(AutoType::new()),
+            Some(x) => AutoType::from_xml(x),
+        }
+    }
+
+    pub fn custom_data(&self) -> Result<CustomData, Error> {
+        match self.elem.find("CustomData") {
+            None    => Ok(CustomData::new()),
+            Some(x) => CustomData::from_xml(x),
+        }
+    }
+
+    /// Returns the previous versions of this entry, oldest first.
+    pub fn history(&self) -> Vec<Entry<'a>> {
+        self.elem.find("History")
+                 .map(|x| x.find_all("Entry").map(Entry::new).collect())
+                 .unwrap_or(vec![])
+    }
+}
+
+/// A mutable view of an `<Entry>` in the database.
+#[derive(Debug)]
+pub struct EntryMut<'a> {
+    elem    : &'a mut Element,
+    version : Version,
+}
+
+impl<'a> EntryMut<'a> {
+    pub fn new(elem : &'a mut Element, version : Version) -> EntryMut<'a> {
+        EntryMut {
+            elem,
+            version,
+        }
+    }
+
+    pub fn as_entry(&self) -> Entry<'_> {
+        Entry::new(self.elem)
+    }
+
+    /// Replaces the custom data. KDBX 3.1 has no custom data on entries.
+    pub fn set_custom_data(&mut self, data : &CustomData) -> Result<(), Error> {
+        if !self.version.at_least(4, 0) {
+            return Err(Error::new("custom data on entries requires KDBX 4"));
+        }
+
+        let e = xml::ordered_child(self.elem, "CustomData", ENTRY_ORDER);
+        data.write_xml(e, &self.version);
+        Ok(())
+    }
+
+    fn set_child_text(&mut self, tag : &str, text : &str) {
+        xml::ordered_child(self.elem, tag, ENTRY_ORDER).set_text(text);
+    }
+
+    fn field_elem(&mut self, key : &str) -> Option<&mut Element> {
+        self.elem.find_all_mut("String")
+                 .find(|x| xml::child_text(x, "Key") == Some(key))
+    }
+
+    /// Sets the value of a string field, adding the field if needed. New
+    /// `Password` fields are protected, as in KeePass's default settings.
+    pub fn set_field(&mut self, key : &str, value : &str) {
+        if self.field_elem(key).is_none() {
+            let mut field = Element::new("String");
+            xml::set_child_text(&mut field, "Key", key);
+            xml::set_child_text(&mut field, "Value", "");
+
+            if "Password" == key {
+                field.find_mut("Value").unwrap()
+                     .set_attr("Protected", xml::format_bool(true));
+            }
+
+            let idx = xml::ordered_index(self.elem, "String", ENTRY_ORDER);
+            xml::insert_child(self.elem, idx, field);
+        }
+
+        self.field_elem(key).unwrap()
+            .find_mut("Value").unwrap()
+            .set_text(value);
+    }
+
+    /// Sets whether a string field is protected, returning `false` if the
+    /// entry has no such field.
+    pub fn set_protected(&mut self, key : &str, protected : bool) -> bool {
+        let value = match self.field_elem(key).and_then(|x| x.find_mut("Value")) {
+            None    => return false,
+            Some(x) => x,
+        };
+
+        if protected {
+            value.set_attr("Protected", xml::format_bool(true));
+        } else {
+            value.remove_attr("Protected");
+        }
+
+        true
+    }
+
+    /// Removes a string field, returning `false` if the entry didn't have it.
+    pub fn remove_field(&mut self, key : &str) -> bool {
+        let idx = self.elem.children()
+                           .position(|x| x.tag().name() == "String"
+                                         && xml::child_text(x, "Key") == Some(key));
+
+        match idx {
+            None    => false,
+            Some(x) => self.elem.remove_child(x).is_some(),
+        }
+    }
+
+    /// Replaces the entry's tags. They are written separated by `;`, the way
+    /// KeePass does.
+    pub fn set_tags<S : AsRef<str>>(&mut self, tags : &[S]) {
+        let joined = tags.iter()
+                         .map(|x| x.as_ref().trim())
+                         .filter(|x| !x.is_empty())
+                         .collect::<Vec<_>>()
+                         .join(";");
+        self.set_child_text("Tags", &joined);
+    }
+
+    /// Adds a tag, returning `false` if the entry already had it.
+    pub fn add_tag(&mut self, tag : &str) -> bool {
+        let mut tags = self.as_entry().tags();
+
+        if tags.iter().any(|x| tags_equal(x, tag)) {
+            return false;
+        }
+
+        tags.push(tag.to_owned());
+        self.set_tags(&tags);
+        true
+    }
+
+    /// Removes a tag, returning `false` if the entry didn't have it.
+    pub fn remove_tag(&mut self, tag : &str) -> bool {
+        let mut tags = self.as_entry().tags();
+        let count = tags.len();
+
+        tags.retain(|x| !tags_equal(x, tag));
+        if tags.len() == count {
+            return false;
+        }
+
+        self.set_tags(&tags);
+        true
+    }
+
+    pub fn set_foreground_color(&mut self, color : Option<&str>) {
+        self.set_child_text("ForegroundColor", color.unwrap_or(""));
+    }
+
+    pub fn set_background_color(&mut self, color : Option<&str>) {
+        self.set_child_text("BackgroundColor", color.unwrap_or(""));
+    }
+
+    pub fn set_override_url(&mut self, url : Option<&str>) {
+        self.set_child_text("OverrideURL", url.unwrap_or(""));
+    }
+
+    /// Sets `QualityCheck`, which was added in KDBX 4.1.
+    pub fn set_quality_check(&mut self, enabled : bool) -> Result<(), Error> {
+        if !self.version.at_least(4, 1) {
+            return Err(Error::new("QualityCheck requires KDBX 4.1"));
+        }
+
+        self.set_child_text("QualityCheck", xml::format_bool(enabled));
+        Ok(())
+    }
+
+    /// Sets `PreviousParentGroup`, which was added in KDBX 4.1. Clearing it
+    /// works with any version.
+    pub fn set_previous_parent_group(&mut self, uuid : Option<&Uuid>) -> Result<(), Error> {
+        match uuid {
+            Some(x) => {
+                if !self.version.at_least(4, 1) {
+                    return Err(Error::new("PreviousParentGroup requires KDBX 4.1"));
+                }
+
+                let encoded = x.to_base64();
+                self.set_child_text("PreviousParentGroup", &encoded);
+            },
+            None    => {
+                if let Some(idx) = self.elem.children()
+                                       .position(|x| x.tag().name() == "PreviousParentGroup") {
+                    self.elem.remove_child(idx);
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Returns every tag used in the database, with the number of entries
+    /// carrying it. Tags that differ only in case are counted together under
+    /// the spelling seen first.
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut counts : BTreeMap<String, usize> = BTreeMap::new();
+
+        for entry in self.entries() {
+            for tag in entry.tags() {
+                let key = counts.keys()
+                                .find(|x| tags_equal(x, &tag))
+                                .cloned()
+                                .unwrap_or(tag);
+                *counts.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        counts
+    }
+
+    pub fn entries_with_tag(&self, tag : &str) -> Vec<Entry<'_>> {
+        self.entries().into_iter().filter(|x| x.has_tag(tag)).collect()
+    }
+}
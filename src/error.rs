@@ -3,6 +3,7 @@ use std::ops::Deref;
 use std::error::Error as StdError;
 use std::convert::From;
 
+use argon2;
 use hex;
 use openssl;
 use elementtree;
@@ -31,6 +32,12 @@ impl From<elementtree::Error> for Error {
     }
 }
 
+impl From<argon2::Error> for Error {
+    fn from(e : argon2::Error) -> Error {
+        Error::from("argon2 error", From::from(e))
+    }
+}
+
 impl From<openssl::error::ErrorStack> for Error {
     fn from(e : openssl::error::ErrorStack) -> Error {
         Error::from("openssl error", From::from(e))
@@ -0,0 +1,78 @@
+use ::{Database, Error, Version};
+use convert;
+
+use std::io::{Read, Write};
+
+use elementtree::Element;
+
+/// The attribute KeePass uses to mark protected values in encrypted files.
+const PROTECTED : &str = "Protected";
+
+/// The attribute KeePass uses instead in plaintext XML files, since nothing
+/// in them is encrypted.
+const PROTECT_IN_MEMORY : &str = "ProtectInMemory";
+
+/// Renames the attribute marking protected values from `from` to `to`.
+fn rename_protected(e : &mut Element, from : &str, to : &str) {
+    let value = e.remove_attr(from);
+    if let Some(value) = value {
+        e.set_attr(to, value);
+    }
+
+    for child in e.children_mut() {
+        rename_protected(child, from, to);
+    }
+}
+
+fn remove_header_hash(doc : &mut Element) {
+    if let Some(meta) = doc.find_mut("Meta") {
+        while let Some(idx) = meta.children()
+                                  .position(|x| x.tag().name() == "HeaderHash") {
+            meta.remove_child(idx);
+        }
+    }
+}
+
+impl Database {
+    /// Writes the database as an unencrypted "KeePass XML (2.x)" file, as
+    /// exported by KeePass. Protected values appear in plaintext and are
+    /// marked with `ProtectInMemory="True"`.
+    ///
+    /// Like KeePass, the binaries are stored in `<Meta>` and timestamps in
+    /// the KDBX 3.1 encoding, whatever version the database is.
+    pub fn export_xml(&self, w : &mut dyn Write) -> Result<(), Error> {
+        let mut db = self.clone();
+        convert::binaries_to_meta(&mut db)?;
+        convert::convert_times(&mut db.xml_doc, &Version { major: 3, minor: 1 });
+
+        remove_header_hash(&mut db.xml_doc);
+        rename_protected(&mut db.xml_doc, PROTECTED, PROTECT_IN_MEMORY);
+
+        db.xml_doc.to_writer(w)?;
+        Ok(())
+    }
+
+    /// Replaces the contents of the database with an unencrypted "KeePass
+    /// XML (2.x)" file, such as one written by `export_xml`. The headers and
+    /// master key stay the same.
+    ///
+    /// The document must pass `validate_xml`.
+    pub fn import_xml(&mut self, r : &mut dyn Read) -> Result<(), Error> {
+        let mut doc = Element::from_reader(r)?;
+        remove_header_hash(&mut doc);
+        rename_protected(&mut doc, PROTECT_IN_MEMORY, PROTECTED);
+        Database::validate_xml(&doc)?;
+
+        let mut db = self.clone();
+        db.xml_doc = doc;
+        db.binaries.clear();
+
+        if 4 <= db.version.major {
+            convert::binaries_to_header(&mut db)?;
+        }
+        convert::convert_times(&mut db.xml_doc, &self.version);
+
+        *self = db;
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use ::{CustomData, Entry, Error, Times, Uuid, Version};
+use xml;
+
+use chrono::Utc;
+use elementtree::Element;
+
+/// The children of a `<Group>`, in the order KeePass writes them.
+pub const GROUP_ORDER : &[&str] = &[
+    "UUID", "Name", "Notes", "IconID", "CustomIconUUID", "Times",
+    "IsExpanded", "DefaultAutoTypeSequence", "EnableAutoType",
+    "EnableSearching", "LastTopVisibleEntry", "PreviousParentGroup", "Tags",
+    "CustomData", "Entry", "Group",
+];
+
+/// Builds an empty group the way KeePass creates one. Auto-type and search
+/// settings are inherited from the parent group.
+pub fn new_element(uuid : &Uuid, name : &str, version : &Version) -> Element {
+    let mut e = Element::new("Group");
+    xml::set_child_text(&mut e, "UUID", uuid.to_base64());
+    xml::set_child_text(&mut e, "Name", name);
+    xml::set_child_text(&mut e, "Notes", "");
+    xml::set_child_text(&mut e, "IconID", "48");
+    Times::new(Utc::now()).write_xml(e.append_new_child("Times"), version);
+    xml::set_child_text(&mut e, "IsExpanded", xml::format_bool(true));
+    xml::set_child_text(&mut e, "DefaultAutoTypeSequence", "");
+    xml::set_child_text(&mut e, "EnableAutoType", "null");
+    xml::set_child_text(&mut e, "EnableSearching", "null");
+    xml::set_child_text(&mut e, "LastTopVisibleEntry", Uuid::nil().to_base64());
+    e
+}
+
+/// A read-only view of a `<Group>` in the database.
+#[derive(Debug,Clone,Copy)]
+pub struct Group<'a> {
+    elem : &'a Element,
+}
+
+impl<'a> Group<'a> {
+    pub fn new(elem : &'a Element) -> Group<'a> {
+        Group {
+            elem,
+        }
+    }
+
+    pub fn uuid(&self) -> Option<Uuid> {
+        xml::item_uuid(self.elem)
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "Name")
+    }
+
+    pub fn notes(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "Notes")
+    }
+
+    pub fn times(&self) -> Result<Times, Error> {
+        self.elem.find("Times")
+                 .ok_or_else(|| Error::new("missing times"))
+                 .and_then(Times::from_xml)
+    }
+
+    /// Returns the group's own `EnableAutoType` setting, or `None` if it is
+    /// inherited from the parent group.
+    pub fn enable_auto_type(&self) -> Option<bool> {
+        xml::child_text(self.elem, "EnableAutoType").and_then(xml::parse_bool)
+    }
+
+    /// Returns the group's own `EnableSearching` setting, or `None` if it is
+    /// inherited from the parent group.
+    pub fn enable_searching(&self) -> Option<bool> {
+        xml::child_text(self.elem, "EnableSearching").and_then(xml::parse_bool)
+    }
+
+    /// Returns whether the group is expanded in the tree view. Unlike the
+    /// auto-type and search settings this is not inherited.
+    pub fn is_expanded(&self) -> bool {
+        xml::child_text(self.elem, "IsExpanded")
+            .and_then(xml::parse_bool)
+            .unwrap_or(true)
+    }
+
+    /// Returns the entry shown at the top of the entry list when the group
+    /// was last viewed.
+    pub fn last_top_visible_entry(&self) -> Option<Uuid> {
+        xml::child_text(self.elem, "LastTopVisibleEntry")
+            .and_then(|x| Uuid::from_base64(x).ok())
+            .and_then(|x| if x.is_nil() { None } else { Some(x) })
+    }
+
+    /// Returns the group's own default auto-type sequence, or `None` if it is
+    /// inherited from the parent group.
+    pub fn default_auto_type_sequence(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "DefaultAutoTypeSequence")
+            .map(|x| x.trim())
+            .and_then(|x| if x.is_empty() { None } else { Some(x) })
+    }
+
+    /// Returns the group this one was moved out of, as recorded by KDBX 4.1.
+    pub fn previous_parent_group(&self) -> Option<Uuid> {
+        xml::child_text(self.elem, "PreviousParentGroup")
+            .and_then(|x| Uuid::from_base64(x).ok())
+    }
+
+    pub fn custom_data(&self) -> Result<CustomData, Error> {
+        match self.elem.find("CustomData") {
+            None    => Ok(CustomData::new()),
+            Some(x) => CustomData::from_xml(x),
+        }
+    }
+
+    /// Returns the groups directly inside this one.
+    pub fn groups(&self) -> Vec<Group<'a>> {
+        self.elem.find_all("Group").map(Group::new).collect()
+    }
+
+    /// Returns the entries directly inside this group.
+    pub fn entries(&self) -> Vec<Entry<'a>> {
+        self.elem.find_all("Entry").map(Entry::new).collect()
+    }
+
+    /// Returns the entries in this group and all of its subgroups.
+    pub fn all_entries(&self) -> Vec<Entry<'a>> {
+        let mut entries = self.entries();
+
+        for group in self.groups() {
+            entries.extend(group.all_entries());
+        }
+
+        entries
+    }
+}
+
+/// A mutable view of a `<Group>` in the database.
+#[derive(Debug)]
+pub struct GroupMut<'a> {
+    elem    : &'a mut Element,
+    version : Version,
+}
+
+impl<'a> GroupMut<'a> {
+    pub fn new(elem : &'a mut Element, version : Version) -> GroupMut<'a> {
+        GroupMut {
+            elem,
+            version,
+        }
+    }
+
+    pub fn as_group(&self) -> Group<'_> {
+        Group::new(self.elem)
+    }
+
+    /// Replaces the custom data. KDBX 3.1 has no custom data on groups.
+    pub fn set_custom_data(&mut self, data : &CustomData) -> Result<(), Error> {
+        if !self.version.at_least(4, 0) {
+            return Err(Error::new("custom data on groups requires KDBX 4"));
+        }
+
+        let e = xml::ordered_child(self.elem, "CustomData", GROUP_ORDER);
+        data.write_xml(e, &self.version);
+        Ok(())
+    }
+
+    fn set_child_text(&mut self, tag : &str, text : &str) {
+        xml::ordered_child(self.elem, tag, GROUP_ORDER).set_text(text);
+    }
+
+    pub fn set_name(&mut self, name : &str) {
+        self.set_child_text("Name", name);
+    }
+
+    pub fn set_notes(&mut self, notes : &str) {
+        self.set_child_text("Notes", notes);
+    }
+
+    /// Sets `EnableAutoType`, where `None` inherits from the parent group.
+    pub fn set_enable_auto_type(&mut self, enabled : Option<bool>) {
+        self.set_child_text("EnableAutoType",
+                            enabled.map(xml::format_bool).unwrap_or("null"));
+    }
+
+    /// Sets `EnableSearching`, where `None` inherits from the parent group.
+    pub fn set_enable_searching(&mut self, enabled : Option<bool>) {
+        self.set_child_text("EnableSearching",
+                            enabled.map(xml::format_bool).unwrap_or("null"));
+    }
+
+    /// Sets the default auto-type sequence, where `None` inherits from the
+    /// parent group.
+    pub fn set_default_auto_type_sequence(&mut self, sequence : Option<&str>) {
+        self.set_child_text("DefaultAutoTypeSequence", sequence.unwrap_or(""));
+    }
+
+    pub fn set_expanded(&mut self, expanded : bool) {
+        self.set_child_text("IsExpanded", xml::format_bool(expanded));
+    }
+
+    pub fn set_last_top_visible_entry(&mut self, uuid : Option<&Uuid>) {
+        let encoded = uuid.cloned().unwrap_or_else(Uuid::nil).to_base64();
+        self.set_child_text("LastTopVisibleEntry", &encoded);
+    }
+}
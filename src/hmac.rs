@@ -0,0 +1,85 @@
+use ::Error;
+
+use byteorder::{LittleEndian, ByteOrder};
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sha;
+use openssl::sign::Signer;
+
+/// The block index KDBX 4 uses to derive the key for the header's HMAC.
+pub const HEADER_INDEX : u64 = u64::MAX;
+
+/// Derives the HMAC key for one block from the 64 byte base key, which is
+/// SHA-512 of the master seed, the transformed key and a 0x01 byte.
+fn block_key(base_key : &[u8], index : u64) -> [u8; 64] {
+    let mut input = vec![0u8; 8];
+    LittleEndian::write_u64(&mut input, index);
+    input.extend_from_slice(base_key);
+    sha::sha512(&input)
+}
+
+fn hmac_sha256(key : &[u8], parts : &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let key = PKey::hmac(key)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+
+    for part in parts {
+        signer.update(part)?;
+    }
+
+    Ok(signer.sign_to_vec()?)
+}
+
+fn sign(base_key : &[u8], index : u64, parts : &[&[u8]]) -> Result<Vec<u8>, Error> {
+    hmac_sha256(&block_key(base_key, index), parts)
+}
+
+/// Computes the HMAC stored after the header of a KDBX 4 file.
+pub fn header_hmac(base_key : &[u8], header : &[u8]) -> Result<Vec<u8>, Error> {
+    sign(base_key, HEADER_INDEX, &[header])
+}
+
+/// Computes the HMAC of one block in the HMAC block stream, which covers the
+/// block's index and size as well as its data.
+pub fn block_hmac(base_key : &[u8], index : u64, data : &[u8]) -> Result<Vec<u8>, Error> {
+    let mut prefix = [0u8; 12];
+    LittleEndian::write_u64(&mut prefix[..8], index);
+    LittleEndian::write_i32(&mut prefix[8..], data.len() as i32);
+
+    sign(base_key, index, &[&prefix, data])
+}
+
+/// Compares two HMACs in constant time.
+pub fn verify(expected : &[u8], actual : &[u8]) -> bool {
+    expected.len() == actual.len() && memcmp::eq(expected, actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex::FromHex;
+
+    #[test]
+    fn rfc4231_case1() {
+        let expected = Vec::from_hex("b0344c61d8db38535ca8afceaf0bf12b\
+                                      881dc200c9833da726e9376c2e32cff7").unwrap();
+        assert_eq!(hmac_sha256(&[0x0b; 20], &[b"Hi There"]).unwrap(), expected);
+    }
+
+    #[test]
+    fn rfc4231_case2() {
+        let expected = Vec::from_hex("5bdcc146bf60754e6a042426089575c7\
+                                      5a003f089d2739839dec58b964ec3843").unwrap();
+        let parts : &[&[u8]] = &[b"what do ya want ", b"for nothing?"];
+        assert_eq!(hmac_sha256(b"Jefe", parts).unwrap(), expected);
+    }
+
+    #[test]
+    fn block() {
+        let base_key : Vec<u8> = (0..64).collect();
+        let expected = Vec::from_hex("09a20c147ad683387c60ab113c3495c0\
+                                      18c8a52ce87005054c50556e5c705d09").unwrap();
+        assert_eq!(block_hmac(&base_key, 3, b"block data").unwrap(), expected);
+    }
+}
@@ -0,0 +1,76 @@
+use ::{Database, Entry, Error, Group, Uuid};
+use autotype::DEFAULT_SEQUENCE;
+
+use elementtree::Element;
+
+/// Walks the groups in `chain` from the innermost one up to the root group,
+/// returning the first setting that isn't inherited.
+fn inherited<'a, T, F>(chain : &[&'a Element], f : F) -> Option<T>
+    where F : Fn(Group<'a>) -> Option<T>
+{
+    chain.iter()
+         .rev()
+         .filter(|x| x.tag().name() == "Group")
+         .filter_map(|x| f(Group::new(x)))
+         .next()
+}
+
+impl Database {
+    /// Finds a group or entry, returning the chain of groups leading to it
+    /// followed by the item itself.
+    fn item_chain(&self, uuid : &Uuid) -> Result<Vec<&Element>, Error> {
+        self.find_item("Group", uuid)
+            .or_else(|| self.find_item("Entry", uuid))
+            .ok_or_else(|| Error::new("item not found"))
+    }
+
+    /// Checks whether auto-type is enabled for a group or entry, taking into
+    /// account the `EnableAutoType` setting inherited through its groups.
+    pub fn auto_type_enabled(&self, uuid : &Uuid) -> Result<bool, Error> {
+        let chain = self.item_chain(uuid)?;
+        let item = chain[chain.len() - 1];
+
+        if item.tag().name() == "Entry" && !Entry::new(item).auto_type()?.enabled {
+            return Ok(false);
+        }
+
+        Ok(inherited(&chain, |x| x.enable_auto_type()).unwrap_or(true))
+    }
+
+    /// Checks whether a group or entry is included in searches, taking into
+    /// account the `EnableSearching` setting inherited through its groups.
+    pub fn searching_enabled(&self, uuid : &Uuid) -> Result<bool, Error> {
+        let chain = self.item_chain(uuid)?;
+        Ok(inherited(&chain, |x| x.enable_searching()).unwrap_or(true))
+    }
+
+    /// Returns the sequence used when a group or entry is auto-typed without
+    /// a more specific association, resolving inheritance through its groups.
+    pub fn default_auto_type_sequence(&self, uuid : &Uuid) -> Result<String, Error> {
+        let chain = self.item_chain(uuid)?;
+        let item = chain[chain.len() - 1];
+
+        if item.tag().name() == "Entry" {
+            let auto_type = Entry::new(item).auto_type()?;
+            if !auto_type.default_sequence.is_empty() {
+                return Ok(auto_type.default_sequence);
+            }
+        }
+
+        Ok(inherited(&chain, |x| x.default_auto_type_sequence())
+               .unwrap_or(DEFAULT_SEQUENCE)
+               .to_owned())
+    }
+
+    /// Returns every entry that searches should consider, leaving out those
+    /// in groups where searching is disabled.
+    pub fn searchable_entries(&self) -> Vec<Entry<'_>> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| match entry.uuid() {
+                None    => false,
+                Some(x) => self.searching_enabled(&x).unwrap_or(false),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,197 @@
+use ::{Error, Uuid};
+use keys::{CompositeKey, Key};
+use variant::{Variant, VariantDictionary};
+
+use argon2;
+
+const KDF_AES : Uuid = Uuid([0xc9, 0xd9, 0xf3, 0x9a,
+                             0x62, 0x8a, 0x44, 0x60,
+                             0xbf, 0x74, 0x0d, 0x08,
+                             0xc1, 0x8a, 0x4f, 0xea]);
+
+const KDF_ARGON2D : Uuid = Uuid([0xef, 0x63, 0x6d, 0xdf,
+                                 0x8c, 0x29, 0x44, 0x4b,
+                                 0x91, 0xf7, 0xa9, 0xa4,
+                                 0x03, 0xe3, 0x0a, 0x0c]);
+
+const KDF_ARGON2ID : Uuid = Uuid([0x9e, 0x29, 0x8b, 0x19,
+                                  0x56, 0xdb, 0x47, 0x73,
+                                  0xb2, 0x3d, 0xfc, 0x3e,
+                                  0xc6, 0xf0, 0xa1, 0xe6]);
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Argon2Variant {
+    Argon2d,
+    Argon2id,
+}
+
+/// The key derivation function used to transform the composite key. The
+/// seed and the number of rounds (iterations for Argon2) are kept in
+/// `Database::transform_seed` and `Database::transform_rounds`.
+///
+/// KDBX 3.1 only supports `Aes`.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Kdf {
+    Aes,
+    Argon2 {
+        variant     : Argon2Variant,
+        /// Memory in bytes.
+        memory      : u64,
+        parallelism : u32,
+        version     : u32,
+    },
+}
+
+/// Maps the KDBX parameters onto the Argon2 configuration. KDBX stores the
+/// memory in bytes, while Argon2 counts it in KiB.
+fn argon2_config<'a>(variant : Argon2Variant, memory : u64, parallelism : u32,
+                     version : u32, rounds : u64) -> Result<argon2::Config<'a>, Error> {
+    if rounds > u32::MAX as u64 || memory / 1024 > u32::MAX as u64 {
+        return Err(Error::new("argon2 parameters out of range"));
+    }
+
+    Ok(argon2::Config {
+        ad: &[],
+        hash_length: 32,
+        lanes: parallelism,
+        mem_cost: (memory / 1024) as u32,
+        secret: &[],
+        time_cost: rounds as u32,
+        variant: match variant {
+            Argon2Variant::Argon2d  => argon2::Variant::Argon2d,
+            Argon2Variant::Argon2id => argon2::Variant::Argon2id,
+        },
+        version: argon2::Version::from_u32(version)?,
+    })
+}
+
+impl Kdf {
+    pub fn uuid(&self) -> Uuid {
+        match *self {
+            Kdf::Aes                                                => KDF_AES,
+            Kdf::Argon2 { variant: Argon2Variant::Argon2d, .. }     => KDF_ARGON2D,
+            Kdf::Argon2 { variant: Argon2Variant::Argon2id, .. }    => KDF_ARGON2ID,
+        }
+    }
+
+    /// Reads the `KdfParameters` header of a KDBX 4 file, returning the KDF
+    /// along with its seed and rounds.
+    pub fn from_parameters(params : &VariantDictionary)
+                           -> Result<(Kdf, Vec<u8>, u64), Error> {
+        let uuid = params.get_bytes("$UUID")
+                         .ok_or_else(|| Error::new("missing kdf uuid"))
+                         .and_then(Uuid::from_slice)?;
+
+        let seed = params.get_bytes("S")
+                         .ok_or_else(|| Error::new("missing kdf seed"))?
+                         .to_vec();
+
+        if uuid == KDF_AES {
+            let rounds = params.get_u64("R")
+                               .ok_or_else(|| Error::new("missing kdf rounds"))?;
+            return Ok((Kdf::Aes, seed, rounds));
+        }
+
+        let variant = if uuid == KDF_ARGON2D {
+            Argon2Variant::Argon2d
+        } else if uuid == KDF_ARGON2ID {
+            Argon2Variant::Argon2id
+        } else {
+            return Err(Error::new("unsupported kdf"));
+        };
+
+        let kdf = Kdf::Argon2 {
+            variant,
+            memory: params.get_u64("M")
+                          .ok_or_else(|| Error::new("missing argon2 memory"))?,
+            parallelism: params.get_u32("P")
+                               .ok_or_else(|| Error::new("missing argon2 parallelism"))?,
+            version: params.get_u32("V")
+                           .ok_or_else(|| Error::new("missing argon2 version"))?,
+        };
+
+        let iterations = params.get_u64("I")
+                               .ok_or_else(|| Error::new("missing argon2 iterations"))?;
+
+        Ok((kdf, seed, iterations))
+    }
+
+    /// Builds the `KdfParameters` header, in the order KeePass writes it.
+    pub fn to_parameters(&self, seed : &[u8], rounds : u64) -> VariantDictionary {
+        let mut params = VariantDictionary::new();
+        params.set("$UUID", Variant::Bytes(self.uuid().as_bytes().to_vec()));
+
+        match *self {
+            Kdf::Aes => {
+                params.set("R", Variant::UInt64(rounds));
+                params.set("S", Variant::Bytes(seed.to_vec()));
+            },
+            Kdf::Argon2 { memory, parallelism, version, .. } => {
+                params.set("S", Variant::Bytes(seed.to_vec()));
+                params.set("P", Variant::UInt32(parallelism));
+                params.set("M", Variant::UInt64(memory));
+                params.set("I", Variant::UInt64(rounds));
+                params.set("V", Variant::UInt32(version));
+            },
+        }
+
+        params
+    }
+
+    /// Transforms the composite key, giving 32 bytes of key material.
+    pub fn transform(&self, key : &CompositeKey, seed : &Vec<u8>, rounds : u64)
+                     -> Result<Vec<u8>, Error> {
+        let (variant, memory, parallelism, version) = match *self {
+            Kdf::Aes => return key.transform(rounds, seed),
+            Kdf::Argon2 { variant, memory, parallelism, version }
+                => (variant, memory, parallelism, version),
+        };
+
+        let config = argon2_config(variant, memory, parallelism, version, rounds)?;
+        Ok(argon2::hash_raw(key.bytes(), seed, &config)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex::FromHex;
+
+    /// The test vectors from RFC 9106, section 5, which also use a secret
+    /// and associated data.
+    fn rfc9106(variant : Argon2Variant) -> Vec<u8> {
+        let mut config = argon2_config(variant, 32 * 1024, 4, 0x13, 3).unwrap();
+        let secret = [3u8; 8];
+        let ad = [4u8; 12];
+        config.secret = &secret;
+        config.ad = &ad;
+
+        argon2::hash_raw(&[1u8; 32], &[2u8; 16], &config).unwrap()
+    }
+
+    #[test]
+    fn argon2d_rfc9106() {
+        let expected = Vec::from_hex("512b391b6f1162975371d30919734294\
+                                      f868e3be3984f3c1a13a4db9fabe4acb").unwrap();
+        assert_eq!(rfc9106(Argon2Variant::Argon2d), expected);
+    }
+
+    #[test]
+    fn argon2id_rfc9106() {
+        let expected = Vec::from_hex("0d640df58d78766c08c037a34a8b53c9\
+                                      d01ef0452d75b65eb52520e96b01e659").unwrap();
+        assert_eq!(rfc9106(Argon2Variant::Argon2id), expected);
+    }
+
+    #[test]
+    fn argon2_out_of_range() {
+        let variant = Argon2Variant::Argon2id;
+        assert!(argon2_config(variant, 1024, 1, 0x13, u32::MAX as u64 + 1).is_err());
+        assert!(argon2_config(variant, (u32::MAX as u64 + 1) * 1024, 1, 0x13, 1).is_err());
+        assert!(argon2_config(variant, 1024, 1, 0x10, 1).is_ok());
+        assert!(argon2_config(variant, 1024, 1, 0x12, 1).is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use ::Error;
 
+use std;
 use std::io::Write;
 
 use unicode_normalization::UnicodeNormalization;
 use elementtree::Element;
 use hex::{FromHex,ToHex};
 use openssl::hash::{Hasher, MessageDigest};
+use openssl::rand;
 use openssl::sha;
 use openssl::symm::{self,Cipher,Crypter};
 
@@ -33,11 +35,19 @@ pub trait Key<'a> {
     }
 }
 
+#[derive(Clone)]
 pub struct CompositeKey {
     hasher : Hasher,
     bytes : Vec<u8>,
 }
 
+// Keeps the key material out of debug output.
+impl std::fmt::Debug for CompositeKey {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "CompositeKey {{ .. }}")
+    }
+}
+
 impl<'a> Key<'a> for CompositeKey {
     fn bytes(&'a self) -> &'a [u8] {
         // TODO: Look into using RefCell to do better caching
@@ -88,8 +98,10 @@ impl PasswordKey {
 #[derive(Debug,PartialEq,Eq)]
 pub enum FileKeyFormat {
     Xml,
+    Xml2,
     Bin32,
     Hex64,
+    Hashed,
 }
 
 #[derive(Debug,PartialEq,Eq)]
@@ -110,9 +122,67 @@ impl FileKey {
         }
     }
 
-    pub fn from_xml(f : &[u8]) -> Option<FileKey> {
+    /// Creates a new random 32 byte key, to be written with `save`.
+    pub fn generate(fmt : FileKeyFormat) -> Result<FileKey, Error> {
+        FileKey::generate_with_entropy(fmt, &[])
+    }
+
+    /// Like `generate`, but also mixes in entropy from the user, such as
+    /// mouse movements or typed text. As in KeePass, the key is the SHA-256
+    /// of the random bytes followed by the entropy.
+    pub fn generate_with_entropy(fmt : FileKeyFormat, entropy : &[u8])
+                                 -> Result<FileKey, Error> {
+        if FileKeyFormat::Hashed == fmt {
+            return Err(Error::new("hashed key files can't be generated"));
+        }
+
+        let mut bytes = vec![0u8; 32];
+        rand::rand_bytes(&mut bytes)?;
+
+        if !entropy.is_empty() {
+            let mut hasher = sha::Sha256::new();
+            hasher.update(&bytes);
+            hasher.update(entropy);
+            bytes = hasher.finish().to_vec();
+        }
+
+        Ok(FileKey::new(fmt, bytes))
+    }
+
+    /// Parses the data of a version 2.0 XML key file, which is hex split
+    /// into groups by whitespace. The optional `Hash` attribute holds the
+    /// first 4 bytes of the SHA-256 of the key, in hex.
+    fn parse_xml2(data : &Element) -> Result<Option<FileKey>, Error> {
+        let hex = data.text()
+                      .chars()
+                      .filter(|x| !x.is_whitespace())
+                      .collect::<String>();
+
+        let bytes : Vec<u8> = match Vec::from_hex(hex) {
+            Err(_)  => return Ok(None),
+            Ok(x)   => x,
+        };
+
+        if let Some(hash) = data.get_attr("Hash") {
+            let expected = match Vec::<u8>::from_hex(hash.trim()) {
+                Err(_)  => return Err(Error::new("malformed key file checksum")),
+                Ok(x)   => x,
+            };
+
+            if expected[..] != sha::sha256(&bytes)[..4] {
+                return Err(Error::new("key file checksum mismatch, the key file is corrupt"));
+            }
+        }
+
+        Ok(Some(FileKey::new(FileKeyFormat::Xml2, bytes)))
+    }
+
+    /// Parses a KeePass XML key file, either version 1.0 with base64 data or
+    /// version 2.0 with hex data. Returns `Ok(None)` if it isn't one, and an
+    /// error if it is a version 2.0 file whose checksum doesn't match.
+    fn parse_xml(f : &[u8]) -> Result<Option<FileKey>, Error> {
         let root = match Element::from_reader(f) {
-            Err(_)  => return None,
+            Err(_)  => return Ok(None),
             Ok(x)   => x
         };
 
@@ -120,16 +190,22 @@ impl FileKey {
                           .and_then(|x| x.find("Version"))
                           .map(|x| x.text().trim());
 
+        let data = match root.find("Key").and_then(|x| x.find("Data")) {
+            None    => return Ok(None),
+            Some(x) => x,
+        };
 
-        if Some("1.00") != version {
-            return None;
+        match version {
+            Some("1.0") | Some("1.00")  => Ok(base64::decode(data.text().trim())
+                                              .ok()
+                                              .map(|x| FileKey::new(FileKeyFormat::Xml, x))),
+            Some("2.0") | Some("2.00")  => FileKey::parse_xml2(data),
+            _                           => Ok(None),
         }
+    }
 
-        root.find("Key")
-            .and_then(|x| x.find("Data"))
-            .map(|x| x.text().trim())
-            .and_then(|x| base64::decode(x).ok())
-            .map(|x| FileKey::new(FileKeyFormat::Xml, x))
+    pub fn from_xml(f : &[u8]) -> Option<FileKey> {
+        FileKey::parse_xml(f).unwrap_or_default()
     }
 
     pub fn from_bin32(f : &[u8]) -> Option<FileKey> {
@@ -148,11 +224,21 @@ impl FileKey {
         Vec::from_hex(f).ok().map(|x| FileKey::new(FileKeyFormat::Hex64, x))
     }
 
+    /// Like KeePass, any file can be used as a key file: the key is the
+    /// SHA-256 of its contents.
+    pub fn from_hashed(f : &[u8]) -> FileKey {
+        FileKey::new(FileKeyFormat::Hashed, sha::sha256(f).to_vec())
+    }
+
+    /// Detects the format of a key file and reads it, falling back to
+    /// hashing the file like KeePass. Fails if an XML key file's checksum
+    /// doesn't match its data.
     pub fn from(bytes : &[u8]) -> Result<FileKey, Error> {
-        FileKey::from_xml(bytes)
-            .or_else(|| FileKey::from_bin32(bytes))
-            .or_else(|| FileKey::from_hex64(bytes))
-            .ok_or_else(|| Error::new("unable to parse key file"))
+        let key = FileKey::parse_xml(bytes)?
+                      .or_else(|| FileKey::from_bin32(bytes))
+                      .or_else(|| FileKey::from_hex64(bytes))
+                      .unwrap_or_else(|| FileKey::from_hashed(bytes));
+        Ok(key)
     }
 
     fn save_xml(&self) -> Vec<u8> {
@@ -168,6 +254,33 @@ impl FileKey {
 "#, base64::encode(&self.bytes)).into_bytes()
     }
 
+    fn save_xml2(&self) -> Vec<u8> {
+        let hex = self.bytes.to_hex().to_uppercase();
+        let hash = sha::sha256(&self.bytes)[..4].to_vec().to_hex().to_uppercase();
+
+        // KeePass writes the data in groups of 8 characters, 4 to a line.
+        let lines = hex.as_bytes()
+                       .chunks(32)
+                       .map(|line| line.chunks(8)
+                                       .map(|x| std::str::from_utf8(x).unwrap())
+                                       .collect::<Vec<_>>()
+                                       .join(" "))
+                       .map(|line| format!("            {}\n", line))
+                       .collect::<String>();
+
+        format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<KeyFile>
+    <Meta>
+        <Version>2.0</Version>
+    </Meta>
+    <Key>
+        <Data Hash="{}">
+{}        </Data>
+    </Key>
+</KeyFile>
+"#, hash, lines).into_bytes()
+    }
+
     fn save_bin32(&self) -> Vec<u8> {
         self.bytes.clone()
     }
@@ -176,11 +289,15 @@ impl FileKey {
         self.bytes.to_hex().into_bytes()
     }
 
+    /// Serializes the key file. The original contents of a hashed key file
+    /// aren't kept, so it is saved as the equivalent `Bin32` file instead.
     pub fn save(&self) -> Vec<u8> {
         match self.format {
             FileKeyFormat::Xml      => self.save_xml(),
+            FileKeyFormat::Xml2     => self.save_xml2(),
             FileKeyFormat::Bin32    => self.save_bin32(),
             FileKeyFormat::Hex64    => self.save_hex64(),
+            FileKeyFormat::Hashed   => self.save_bin32(),
         }
     }
 }
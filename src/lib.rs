@@ -5,20 +5,79 @@ extern crate openssl;
 extern crate flate2;
 extern crate base64;
 extern crate hex;
+extern crate chrono;
+extern crate argon2;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
 
 pub mod keys;
 mod error;
 mod reader;
+mod writer;
+mod xml;
+mod times;
+mod entry;
+mod group;
+mod deleted;
+mod autotype;
+mod customdata;
+mod meta;
+mod salsa20;
+mod chacha20;
+mod stream;
+mod validate;
+mod uuid;
+mod path;
+mod recycle;
+mod moves;
+mod edit;
+mod template;
+mod inherit;
+mod data;
+mod variant;
+mod kdf;
+mod hmac;
+mod save;
+mod masterkey;
+mod convert;
+mod export;
 
 use std::collections::HashMap;
+use std::io::Write;
 
 use elementtree::Element;
+use keys::CompositeKey;
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::rand;
+use openssl::symm;
+use chacha20::ChaCha20;
+use stream::InnerStream;
 
 pub use error::Error;
 pub use reader::Reader;
+pub use writer::Writer;
+pub use uuid::Uuid;
+pub use path::PathSyntax;
+pub use times::Times;
+pub use entry::{Entry, EntryMut};
+pub use group::{Group, GroupMut};
+pub use meta::{Meta, MetaMut};
+pub use deleted::DeletedObject;
+pub use autotype::{AutoType, AutoTypeAssociation};
+pub use customdata::{CustomData, CustomDataItem};
+pub use variant::{Variant, VariantDictionary};
+pub use xml::insert_child;
+pub use kdf::{Argon2Variant, Kdf};
+pub use data::{DatabaseData, DatabaseHeader, EntryData, GroupData, MetaData,
+               ProtectedValues, StringField};
 
 #[derive(Debug,PartialEq,Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Header {
     End=0u8,
     Comment=1u8,
@@ -31,9 +90,23 @@ pub enum Header {
     ProtectedStreamKey=8u8,
     StreamStartBytes=9u8,
     InnerRandomStreamId=10u8,
+    KdfParameters=11u8,
+    PublicCustomData=12u8,
 }
 
+/// The fields of the inner header, which KDBX 4 stores in front of the XML.
 #[derive(Debug,PartialEq,Eq)]
+#[repr(u8)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InnerHeader {
+    End=0u8,
+    InnerRandomStreamId=1u8,
+    InnerRandomStreamKey=2u8,
+    Binary=3u8,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Compression {
     None,
     GZip,
@@ -47,12 +120,22 @@ impl Compression {
             _ => return Err(Error::new("unsupported compression")),
         }
     }
+
+    /// The value stored in the `CompressionFlags` header.
+    pub fn id(&self) -> u32 {
+        match *self {
+            Compression::None => 0,
+            Compression::GZip => 1,
+        }
+    }
 }
 
-#[derive(Debug,PartialEq,Eq)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InnerStreamCipher {
     None,
     Salsa20,
+    ChaCha20,
 }
 
 impl InnerStreamCipher {
@@ -60,29 +143,95 @@ impl InnerStreamCipher {
         match v {
             0 => Ok(InnerStreamCipher::None),
             2 => Ok(InnerStreamCipher::Salsa20),
+            3 => Ok(InnerStreamCipher::ChaCha20),
             _ => return Err(Error::new("unsupported inner cipher")),
         }
     }
+
+    /// The value stored in the `InnerRandomStreamId` header, or the inner
+    /// header of KDBX 4.
+    pub fn id(&self) -> u32 {
+        match *self {
+            InnerStreamCipher::None     => 0,
+            InnerStreamCipher::Salsa20  => 2,
+            InnerStreamCipher::ChaCha20 => 3,
+        }
+    }
 }
 
-#[derive(Debug,PartialEq,Eq)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OuterCipher {
     Aes128,
+    /// ChaCha20 from RFC 7539, only supported by KDBX 4.
+    ChaCha20,
 }
 
-const CIPHERID_AES128 : [u8; 16] = [0x31, 0xc1, 0xf2, 0xe6,
-                                    0xbf, 0x71, 0x43, 0x50,
-                                    0xbe, 0x58, 0x05, 0x21,
-                                    0x6a, 0xfc, 0x5a, 0xff];
+const CIPHERID_AES128 : Uuid = Uuid([0x31, 0xc1, 0xf2, 0xe6,
+                                     0xbf, 0x71, 0x43, 0x50,
+                                     0xbe, 0x58, 0x05, 0x21,
+                                     0x6a, 0xfc, 0x5a, 0xff]);
+
+const CIPHERID_CHACHA20 : Uuid = Uuid([0xd6, 0x03, 0x8a, 0x2b,
+                                       0x8b, 0x6f, 0x4c, 0xb5,
+                                       0xa5, 0x24, 0x33, 0x9a,
+                                       0x31, 0xdb, 0xb5, 0x9a]);
 
 impl OuterCipher {
-    pub fn from(v : &[u8]) -> Result<OuterCipher, Error> {
-        if v == CIPHERID_AES128 {
+    pub fn from(v : &Uuid) -> Result<OuterCipher, Error> {
+        if *v == CIPHERID_AES128 {
             Ok(OuterCipher::Aes128)
+        } else if *v == CIPHERID_CHACHA20 {
+            Ok(OuterCipher::ChaCha20)
         } else {
             Err(Error::new("unknown cipher"))
         }
     }
+
+    pub fn uuid(&self) -> Uuid {
+        match *self {
+            OuterCipher::Aes128     => CIPHERID_AES128,
+            OuterCipher::ChaCha20   => CIPHERID_CHACHA20,
+        }
+    }
+
+    /// The length of the `EncryptionIv` the cipher uses.
+    pub fn iv_len(&self) -> usize {
+        match *self {
+            OuterCipher::Aes128     => 16,
+            OuterCipher::ChaCha20   => 12,
+        }
+    }
+
+    fn check_iv(&self, iv : &[u8]) -> Result<(), Error> {
+        if iv.len() == self.iv_len() {
+            Ok(())
+        } else {
+            Err(Error::new("invalid encryption iv"))
+        }
+    }
+
+    fn encrypt(&self, key : &[u8], iv : &[u8], data : &[u8]) -> Result<Vec<u8>, Error> {
+        self.check_iv(iv)?;
+
+        match *self {
+            OuterCipher::Aes128 => Ok(symm::encrypt(symm::Cipher::aes_256_cbc(),
+                                                    key, Some(iv), data)?),
+            OuterCipher::ChaCha20 => {
+                let mut out = data.to_vec();
+                ChaCha20::new(key, iv).apply(&mut out)?;
+                Ok(out)
+            },
+        }
+    }
+
+    fn decrypt(&self, key : &[u8], iv : &[u8], data : &[u8]) -> Result<Vec<u8>, Error> {
+        match *self {
+            OuterCipher::Aes128 => Ok(symm::decrypt(symm::Cipher::aes_256_cbc(),
+                                                    key, Some(iv), data)?),
+            OuterCipher::ChaCha20 => self.encrypt(key, iv, data),
+        }
+    }
 }
 
 pub trait HashMapExt {
@@ -102,18 +251,37 @@ impl HashMapExt for HashMap<u8, Vec<u8>> {
     }
 }
 
-#[derive(Debug,PartialEq,Eq)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Version {
     pub major : u16,
     pub minor : u16,
 }
 
-#[derive(Debug)]
+impl Version {
+    /// Checks whether this version is `major.minor` or later.
+    pub fn at_least(&self, major : u16, minor : u16) -> bool {
+        self.major > major || (self.major == major && self.minor >= minor)
+    }
+}
+
+/// An attachment stored in the inner header of a KDBX 4 file. Entries refer
+/// to binaries by their index.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Binary {
+    /// Whether KeePass should keep the data protected in memory.
+    pub protected   : bool,
+    pub data        : Vec<u8>,
+}
+
+#[derive(Clone)]
 pub struct Database {
     pub version             : Version,
     pub compression         : Compression,
     pub outer_cipher        : OuterCipher,
     pub master_seed         : Vec<u8>,
+    pub kdf                 : Kdf,
     pub transform_seed      : Vec<u8>,
     pub transform_rounds    : u64,
     pub encryption_iv       : Vec<u8>,
@@ -121,8 +289,236 @@ pub struct Database {
     pub inner_stream_cipher : InnerStreamCipher,
     pub stream_start_bytes  : Vec<u8>,
     pub other_headers       : Vec<(u8, Vec<u8>)>,
+    pub public_custom_data  : VariantDictionary,
+    pub binaries            : Vec<Binary>,
+    pub other_inner_headers : Vec<(u8, Vec<u8>)>,
+    key                     : Option<CompositeKey>,
     xml_doc                 : Element,
+
+    /// The version whose timestamp encoding `xml_doc` uses.
+    xml_version             : Version,
+}
+
+// Keeps the decrypted document and the key protecting it out of debug output.
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Database")
+         .field("version", &self.version)
+         .field("compression", &self.compression)
+         .field("outer_cipher", &self.outer_cipher)
+         .field("master_seed", &self.master_seed)
+         .field("kdf", &self.kdf)
+         .field("transform_seed", &self.transform_seed)
+         .field("transform_rounds", &self.transform_rounds)
+         .field("encryption_iv", &self.encryption_iv)
+         .field("inner_stream_cipher", &self.inner_stream_cipher)
+         .field("stream_start_bytes", &self.stream_start_bytes)
+         .field("other_headers", &self.other_headers)
+         .field("public_custom_data", &self.public_custom_data)
+         .field("binaries", &self.binaries)
+         .field("other_inner_headers", &self.other_inner_headers)
+         .field("key", &self.key)
+         .finish()
+    }
+}
+
+/// The random values a database is encrypted with. KeePass replaces them
+/// every time it saves.
+struct Seeds {
+    master_seed         : Vec<u8>,
+    transform_seed      : Vec<u8>,
+    encryption_iv       : Vec<u8>,
+    inner_stream_key    : Option<Vec<u8>>,
+    stream_start_bytes  : Vec<u8>,
+}
+
+impl Seeds {
+    /// Derives the key for the outer cipher from the transformed key and the
+    /// master seed.
+    fn master_key(&self, transformed_key : &[u8]) -> Result<Vec<u8>, Error> {
+        let mut hasher = Hasher::new(MessageDigest::sha256())?;
+        hasher.write_all(&self.master_seed)?;
+        hasher.write_all(transformed_key)?;
+
+        Ok(hasher.finish2()?.to_vec())
+    }
+
+    /// Derives the base key for the HMACs in KDBX 4 files.
+    fn hmac_key(&self, transformed_key : &[u8]) -> Result<Vec<u8>, Error> {
+        let mut hasher = Hasher::new(MessageDigest::sha512())?;
+        hasher.write_all(&self.master_seed)?;
+        hasher.write_all(transformed_key)?;
+        hasher.write_all(&[0x01])?;
+
+        Ok(hasher.finish2()?.to_vec())
+    }
 }
 
 impl Database {
+    /// Returns the decrypted XML document. Protected values appear in
+    /// plaintext and keep their `Protected="True"` attribute.
+    pub fn xml(&self) -> &Element {
+        &self.xml_doc
+    }
+
+    /// Checks that a document is structurally valid KeePass XML.
+    pub fn validate_xml(doc : &Element) -> Result<(), Error> {
+        validate::validate(doc)
+    }
+
+    /// Replaces the XML document, provided it passes `validate_xml`.
+    pub fn set_xml(&mut self, doc : Element) -> Result<(), Error> {
+        Database::validate_xml(&doc)?;
+        self.xml_doc = doc;
+        Ok(())
+    }
+
+    /// Edits a copy of the XML document, which only replaces the current one
+    /// if it passes `validate_xml` afterwards.
+    pub fn edit_xml<F>(&mut self, f : F) -> Result<(), Error>
+        where F : FnOnce(&mut Element)
+    {
+        let mut doc = self.xml_doc.clone();
+        f(&mut doc);
+        self.set_xml(doc)
+    }
+
+    pub fn meta(&self) -> Option<Meta<'_>> {
+        self.xml_doc.find("Meta").map(Meta::new)
+    }
+
+    pub fn meta_mut(&mut self) -> Option<MetaMut<'_>> {
+        let version = self.version;
+        self.xml_doc.find_mut("Meta").map(|x| MetaMut::new(x, version))
+    }
+
+    pub fn root_group(&self) -> Option<Group<'_>> {
+        self.xml_doc.find("Root")
+                    .and_then(|x| x.find("Group"))
+                    .map(Group::new)
+    }
+
+    /// Returns every entry in the database, excluding history.
+    pub fn entries(&self) -> Vec<Entry<'_>> {
+        self.root_group()
+            .map(|x| x.all_entries())
+            .unwrap_or(vec![])
+    }
+
+    fn find_item(&self, tag : &str, uuid : &Uuid) -> Option<Vec<&Element>> {
+        self.xml_doc.find("Root")
+                    .and_then(|x| x.find("Group"))
+                    .and_then(|x| xml::find_item(x, tag, uuid))
+    }
+
+    /// Returns the UUIDs of the groups leading to an item, starting with the
+    /// root group, followed by the item's own UUID.
+    fn item_path(&self, tag : &str, uuid : &Uuid) -> Result<Vec<Uuid>, Error> {
+        self.find_item(tag, uuid)
+            .ok_or_else(|| Error::new("item not found"))
+            .map(|x| x.iter().filter_map(|x| xml::item_uuid(x)).collect())
+    }
+
+    pub fn group(&self, uuid : &Uuid) -> Option<Group<'_>> {
+        self.find_item("Group", uuid)
+            .and_then(|x| x.last().cloned())
+            .map(Group::new)
+    }
+
+    pub fn entry(&self, uuid : &Uuid) -> Option<Entry<'_>> {
+        self.find_item("Entry", uuid)
+            .and_then(|x| x.last().cloned())
+            .map(Entry::new)
+    }
+
+    pub fn group_mut(&mut self, uuid : &Uuid) -> Option<GroupMut<'_>> {
+        let version = self.version;
+        self.root_group_elem_mut()
+            .ok()
+            .and_then(|x| xml::find_item_mut(x, "Group", uuid))
+            .map(|x| GroupMut::new(x, version))
+    }
+
+    pub fn entry_mut(&mut self, uuid : &Uuid) -> Option<EntryMut<'_>> {
+        let version = self.version;
+        self.root_group_elem_mut()
+            .ok()
+            .and_then(|x| xml::find_item_mut(x, "Entry", uuid))
+            .map(|x| EntryMut::new(x, version))
+    }
+
+    /// The seeds, IVs and keys stored in the database.
+    fn seeds(&self) -> Seeds {
+        Seeds {
+            master_seed: self.master_seed.clone(),
+            transform_seed: self.transform_seed.clone(),
+            encryption_iv: self.encryption_iv.clone(),
+            inner_stream_key: self.inner_stream_key.clone(),
+            stream_start_bytes: self.stream_start_bytes.clone(),
+        }
+    }
+
+    /// Fresh random seeds, IVs and keys for the ciphers and version the
+    /// database uses.
+    fn random_seeds(&self) -> Result<Seeds, Error> {
+        fn random(len : usize) -> Result<Vec<u8>, Error> {
+            let mut bytes = vec![0u8; len];
+            rand::rand_bytes(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        let inner_stream_key = match self.inner_stream_cipher {
+            InnerStreamCipher::None     => None,
+            InnerStreamCipher::Salsa20  => Some(random(32)?),
+            InnerStreamCipher::ChaCha20 => Some(random(64)?),
+        };
+
+        // KDBX 4 relies on the HMACs instead of the stream start bytes.
+        let stream_start_bytes = match self.version.major {
+            3 => random(32)?,
+            _ => vec![],
+        };
+
+        Ok(Seeds {
+            master_seed: random(32)?,
+            transform_seed: random(32)?,
+            encryption_iv: random(self.outer_cipher.iv_len())?,
+            inner_stream_key,
+            stream_start_bytes,
+        })
+    }
+
+    /// Replaces the master seed, transform seed, encryption IV, protected
+    /// stream key and stream start bytes with fresh random values, as KeePass
+    /// does every time it saves.
+    pub fn randomize_seeds(&mut self) -> Result<(), Error> {
+        let seeds = self.random_seeds()?;
+
+        self.master_seed = seeds.master_seed;
+        self.transform_seed = seeds.transform_seed;
+        self.encryption_iv = seeds.encryption_iv;
+        self.inner_stream_key = seeds.inner_stream_key;
+        self.stream_start_bytes = seeds.stream_start_bytes;
+        Ok(())
+    }
+
+    /// Runs the composite key through the key derivation function.
+    fn transformed_key(&self, seeds : &Seeds, key : &CompositeKey) -> Result<Vec<u8>, Error> {
+        self.kdf.transform(key, &seeds.transform_seed, self.transform_rounds)
+    }
+
+    /// Creates the inner stream used to protect values in the XML payload.
+    fn inner_stream(&self, seeds : &Seeds) -> Result<InnerStream, Error> {
+        match (self.inner_stream_cipher, seeds.inner_stream_key.as_ref()) {
+            (InnerStreamCipher::None, _)    => Ok(InnerStream::new(&self.inner_stream_cipher, &[])),
+            (x, Some(key))                  => Ok(InnerStream::new(&x, key)),
+            (_, None)                       => Err(Error::new("missing protected stream key")),
+        }
+    }
+
+    fn root_group_elem_mut(&mut self) -> Result<&mut Element, Error> {
+        self.xml_doc.find_mut("Root")
+                    .and_then(|x| x.find_mut("Group"))
+                    .ok_or_else(|| Error::new("missing root group"))
+    }
 }
@@ -0,0 +1,54 @@
+use ::{Database, Error, Writer};
+use keys::{CompositeKey, Key};
+
+use std::io::Write;
+use std::path::Path;
+
+use chrono::Utc;
+use openssl::memcmp;
+
+impl Database {
+    fn current_key(&self) -> Result<&CompositeKey, Error> {
+        self.key.as_ref().ok_or_else(|| Error::new("database has no master key"))
+    }
+
+    /// Replaces the master key used by `write_to` and `save_to_path`. Like
+    /// KeePass, this stamps `MasterKeyChanged` and generates new seeds.
+    ///
+    /// If `current` is given, it must match the key the database was opened
+    /// with, or last rekeyed to.
+    pub fn rekey(&mut self, key : CompositeKey, current : Option<&CompositeKey>)
+                 -> Result<(), Error> {
+        if let Some(current) = current {
+            let matches = {
+                let expected = self.current_key()?.bytes();
+                let actual = current.bytes();
+                expected.len() == actual.len() && memcmp::eq(expected, actual)
+            };
+
+            if !matches {
+                return Err(Error::new("current master key does not match"));
+            }
+        }
+
+        self.meta_mut()
+            .ok_or_else(|| Error::new("missing meta"))?
+            .set_master_key_changed(&Utc::now());
+
+        self.randomize_seeds()?;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    /// Writes the database with its current master key.
+    pub fn write_to(&self, w : &mut dyn Write) -> Result<(), Error> {
+        Writer::new(self.current_key()?.clone()).write_to(self, w)
+    }
+
+    /// Saves the database with its current master key, as
+    /// `Writer::save_to_path` does.
+    pub fn save_to_path<P : AsRef<Path>>(&self, path : P, backups : usize)
+                                         -> Result<(), Error> {
+        Writer::new(self.current_key()?.clone()).save_to_path(self, path, backups)
+    }
+}
@@ -0,0 +1,172 @@
+use ::{CustomData, Error, Uuid, Version};
+use times;
+use xml;
+
+use chrono::{DateTime, Utc};
+use elementtree::Element;
+
+/// The children of `<Meta>`, in the order KeePass writes them.
+pub const META_ORDER : &[&str] = &[
+    "Generator", "HeaderHash", "SettingsChanged", "DatabaseName",
+    "DatabaseNameChanged", "DatabaseDescription", "DatabaseDescriptionChanged",
+    "DefaultUserName", "DefaultUserNameChanged", "MaintenanceHistoryDays",
+    "Color", "MasterKeyChanged", "MasterKeyChangeRec", "MasterKeyChangeForce",
+    "MasterKeyChangeForceOnce", "MemoryProtection", "CustomIcons",
+    "RecycleBinEnabled", "RecycleBinUUID", "RecycleBinChanged",
+    "EntryTemplatesGroup", "EntryTemplatesGroupChanged", "HistoryMaxItems",
+    "HistoryMaxSize", "LastSelectedGroup", "LastTopVisibleGroup", "Binaries",
+    "CustomData",
+];
+
+/// A read-only view of the database's `<Meta>` block.
+#[derive(Debug,Clone,Copy)]
+pub struct Meta<'a> {
+    elem : &'a Element,
+}
+
+impl<'a> Meta<'a> {
+    pub fn new(elem : &'a Element) -> Meta<'a> {
+        Meta {
+            elem,
+        }
+    }
+
+    /// Returns the name of the application that last saved the database.
+    pub fn generator(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "Generator")
+    }
+
+    pub fn database_name(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "DatabaseName")
+    }
+
+    pub fn database_description(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "DatabaseDescription")
+    }
+
+    pub fn default_user_name(&self) -> Option<&'a str> {
+        xml::child_text(self.elem, "DefaultUserName")
+    }
+
+    pub fn custom_data(&self) -> Result<CustomData, Error> {
+        match self.elem.find("CustomData") {
+            None    => Ok(CustomData::new()),
+            Some(x) => CustomData::from_xml(x),
+        }
+    }
+
+    /// Whether deleted items go to the recycle bin. KeePass enables it by
+    /// default.
+    pub fn recycle_bin_enabled(&self) -> bool {
+        xml::child_text(self.elem, "RecycleBinEnabled")
+            .and_then(xml::parse_bool)
+            .unwrap_or(true)
+    }
+
+    /// Returns the UUID of the recycle bin group, or `None` if there isn't
+    /// one yet.
+    pub fn recycle_bin_uuid(&self) -> Option<Uuid> {
+        xml::child_text(self.elem, "RecycleBinUUID")
+            .and_then(|x| Uuid::from_base64(x).ok())
+            .and_then(|x| if x.is_nil() { None } else { Some(x) })
+    }
+
+    /// Returns the UUID of the group holding entry templates, if one is set.
+    pub fn entry_templates_group(&self) -> Option<Uuid> {
+        xml::child_text(self.elem, "EntryTemplatesGroup")
+            .and_then(|x| Uuid::from_base64(x).ok())
+            .and_then(|x| if x.is_nil() { None } else { Some(x) })
+    }
+
+    /// Returns how many history items each entry keeps, or `None` if there
+    /// is no limit. KeePass keeps 10 unless configured otherwise.
+    pub fn history_max_items(&self) -> Option<usize> {
+        match xml::child_text(self.elem, "HistoryMaxItems") {
+            None    => Some(10),
+            Some(x) => x.trim().parse::<i64>().ok()
+                        .and_then(|x| if x < 0 { None } else { Some(x as usize) }),
+        }
+    }
+
+    /// Returns the total size in bytes the history of each entry may take
+    /// up, or `None` if there is no limit. KeePass allows 6 MiB unless
+    /// configured otherwise.
+    pub fn history_max_size(&self) -> Option<u64> {
+        match xml::child_text(self.elem, "HistoryMaxSize") {
+            None    => Some(6 * 1024 * 1024),
+            Some(x) => x.trim().parse::<i64>().ok()
+                        .and_then(|x| if x < 0 { None } else { Some(x as u64) }),
+        }
+    }
+
+    pub fn master_key_changed(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        match xml::child_text(self.elem, "MasterKeyChanged") {
+            None    => Ok(None),
+            Some(x) => times::parse_time(x).map(Some),
+        }
+    }
+
+    pub fn recycle_bin_changed(&self) -> Result<Option<DateTime<Utc>>, Error> {
+        match xml::child_text(self.elem, "RecycleBinChanged") {
+            None    => Ok(None),
+            Some(x) => times::parse_time(x).map(Some),
+        }
+    }
+}
+
+/// A mutable view of the database's `<Meta>` block.
+#[derive(Debug)]
+pub struct MetaMut<'a> {
+    elem    : &'a mut Element,
+    version : Version,
+}
+
+impl<'a> MetaMut<'a> {
+    pub fn new(elem : &'a mut Element, version : Version) -> MetaMut<'a> {
+        MetaMut {
+            elem,
+            version,
+        }
+    }
+
+    pub fn as_meta(&self) -> Meta<'_> {
+        Meta::new(self.elem)
+    }
+
+    pub fn set_custom_data(&mut self, data : &CustomData) {
+        let e = xml::ordered_child(self.elem, "CustomData", META_ORDER);
+        data.write_xml(e, &self.version);
+    }
+
+    fn set_child_text(&mut self, tag : &str, text : &str) {
+        xml::ordered_child(self.elem, tag, META_ORDER).set_text(text);
+    }
+
+    pub fn set_master_key_changed(&mut self, at : &DateTime<Utc>) {
+        let text = times::format_time(at, &self.version);
+        self.set_child_text("MasterKeyChanged", &text);
+    }
+
+    pub fn set_recycle_bin_enabled(&mut self, enabled : bool) {
+        self.set_child_text("RecycleBinEnabled", xml::format_bool(enabled));
+    }
+
+    /// Sets the recycle bin group and stamps `RecycleBinChanged`.
+    pub fn set_recycle_bin_uuid(&mut self, uuid : Option<&Uuid>) {
+        let encoded = uuid.cloned().unwrap_or_else(Uuid::nil).to_base64();
+        let now = times::format_time(&Utc::now(), &self.version);
+
+        self.set_child_text("RecycleBinUUID", &encoded);
+        self.set_child_text("RecycleBinChanged", &now);
+    }
+
+    /// Sets the entry templates group and stamps
+    /// `EntryTemplatesGroupChanged`.
+    pub fn set_entry_templates_group(&mut self, uuid : Option<&Uuid>) {
+        let encoded = uuid.cloned().unwrap_or_else(Uuid::nil).to_base64();
+        let now = times::format_time(&Utc::now(), &self.version);
+
+        self.set_child_text("EntryTemplatesGroup", &encoded);
+        self.set_child_text("EntryTemplatesGroupChanged", &now);
+    }
+}
@@ -0,0 +1,73 @@
+use ::{Database, Error, Times, Uuid};
+use entry::ENTRY_ORDER;
+use group::GROUP_ORDER;
+use xml;
+
+use chrono::Utc;
+
+impl Database {
+    /// Moves a group or entry into the `target` group, stamping its
+    /// `LocationChanged` time and, for KDBX 4.1, its `PreviousParentGroup`.
+    /// Entries are placed after the target's entries and groups after its
+    /// groups, so the order of the existing children is kept. Moving an
+    /// item into the group it is already in does nothing.
+    fn relocate(&mut self, tag : &str, uuid : &Uuid, target : &Uuid)
+                -> Result<(), Error> {
+        let path = self.item_path(tag, uuid)?;
+        let target_path = self.item_path("Group", target)
+                              .map_err(|_| Error::new("target group not found"))?;
+
+        if path.len() < 2 {
+            return Err(Error::new("cannot move the root group"));
+        }
+
+        if "Group" == tag && target_path.contains(uuid) {
+            return Err(Error::new("cannot move a group into itself"));
+        }
+
+        let version = self.version;
+        let parent = path[path.len() - 2];
+        if parent == *target {
+            return Ok(());
+        }
+
+        let order = if "Entry" == tag { ENTRY_ORDER } else { GROUP_ORDER };
+
+        // Work on a copy, so the item stays where it is if its times are
+        // malformed.
+        let root = self.root_group_elem_mut()?;
+        let mut elem = xml::find_item(root, tag, uuid)
+                           .and_then(|x| x.last().cloned().cloned())
+                           .unwrap();
+
+        let now = Utc::now();
+        let mut times = match elem.find("Times") {
+            None    => Times::new(now),
+            Some(x) => Times::from_xml(x)?,
+        };
+        times.location_changed = Some(now);
+        times.write_xml(xml::ordered_child(&mut elem, "Times", order), &version);
+
+        if version.at_least(4, 1) {
+            xml::ordered_child(&mut elem, "PreviousParentGroup", order)
+                .set_text(parent.to_base64());
+        }
+
+        xml::take_item(root, tag, uuid);
+        let dest = xml::find_item_mut(root, "Group", target).unwrap();
+        xml::append_item(dest, elem);
+
+        Ok(())
+    }
+
+    /// Moves an entry into another group.
+    pub fn move_entry(&mut self, uuid : &Uuid, target : &Uuid) -> Result<(), Error> {
+        self.relocate("Entry", uuid, target)
+    }
+
+    /// Moves a group, along with everything in it, into another group. Fails
+    /// if `target` is the group itself or one of its subgroups.
+    pub fn move_group(&mut self, uuid : &Uuid, target : &Uuid) -> Result<(), Error> {
+        self.relocate("Group", uuid, target)
+    }
+}
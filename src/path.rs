@@ -0,0 +1,146 @@
+use ::{Database, Entry, Error, Group, Uuid};
+use xml;
+
+/// Describes how a path such as `Root/Infra/Prod/postgres` is split into
+/// group and entry names.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct PathSyntax {
+    pub separator   : char,
+
+    /// Makes the following character part of the name, so that names can
+    /// contain the separator. `None` disables escaping.
+    pub escape      : Option<char>,
+}
+
+impl PathSyntax {
+    /// Slash separated, with backslash as the escape character.
+    pub fn new() -> PathSyntax {
+        PathSyntax {
+            separator: '/',
+            escape: Some('\\'),
+        }
+    }
+
+    pub fn split(&self, path : &str) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        let mut current = String::new();
+        let mut chars = path.chars();
+
+        while let Some(c) = chars.next() {
+            if Some(c) == self.escape {
+                match chars.next() {
+                    None    => return Err(Error::new("path ends with an escape")),
+                    Some(x) => current.push(x),
+                }
+            } else if c == self.separator {
+                names.push(current);
+                current = String::new();
+            } else {
+                current.push(c);
+            }
+        }
+
+        names.push(current);
+
+        if names.iter().any(|x| x.is_empty()) {
+            return Err(Error::new("empty path component"));
+        }
+
+        Ok(names)
+    }
+
+    pub fn join<S : AsRef<str>>(&self, names : &[S]) -> String {
+        let mut path = String::new();
+
+        for (idx, name) in names.iter().enumerate() {
+            if 0 != idx {
+                path.push(self.separator);
+            }
+
+            for c in name.as_ref().chars() {
+                if let Some(escape) = self.escape {
+                    if c == escape || c == self.separator {
+                        path.push(escape);
+                    }
+                }
+                path.push(c);
+            }
+        }
+
+        path
+    }
+}
+
+impl Default for PathSyntax {
+    fn default() -> PathSyntax {
+        PathSyntax::new()
+    }
+}
+
+fn only<T>(mut found : Vec<T>) -> Result<T, Error> {
+    match found.len() {
+        0 => Err(Error::new("path not found")),
+        1 => Ok(found.remove(0)),
+        _ => Err(Error::new("ambiguous path")),
+    }
+}
+
+impl Database {
+    /// Resolves a list of group names, starting with the root group's name.
+    fn group_by_names(&self, names : &[String]) -> Result<Group<'_>, Error> {
+        let (first, rest) = names.split_first()
+                                 .ok_or_else(|| Error::new("empty path"))?;
+
+        let mut group = self.root_group()
+                            .ok_or_else(|| Error::new("missing root group"))?;
+
+        if group.name() != Some(&first[..]) {
+            return Err(Error::new("path not found"));
+        }
+
+        for name in rest {
+            group = only(group.groups()
+                              .into_iter()
+                              .filter(|x| x.name() == Some(&name[..]))
+                              .collect())?;
+        }
+
+        Ok(group)
+    }
+
+    /// Finds the group at `path`, whose first component is the name of the
+    /// root group. Fails if any component matches more than one group.
+    pub fn group_by_path(&self, path : &str, syntax : &PathSyntax)
+                         -> Result<Group<'_>, Error> {
+        self.group_by_names(&syntax.split(path)?)
+    }
+
+    /// Finds the entry at `path`, whose last component is the entry's title.
+    /// Fails if any component matches more than one group or entry.
+    pub fn entry_by_path(&self, path : &str, syntax : &PathSyntax)
+                         -> Result<Entry<'_>, Error> {
+        let names = syntax.split(path)?;
+        let (title, groups) = names.split_last().unwrap();
+
+        let group = self.group_by_names(groups)?;
+        only(group.entries()
+                  .into_iter()
+                  .filter(|x| x.title() == Some(&title[..]))
+                  .collect())
+    }
+
+    /// Returns the full path of a group or entry.
+    pub fn path_of(&self, uuid : &Uuid, syntax : &PathSyntax) -> Option<String> {
+        let chain = self.find_item("Group", uuid)
+                        .or_else(|| self.find_item("Entry", uuid))?;
+
+        let names : Vec<&str> = chain.iter()
+            .map(|x| match x.tag().name() {
+                "Entry" => Entry::new(x).title().unwrap_or(""),
+                _       => xml::child_text(x, "Name").unwrap_or(""),
+            })
+            .collect();
+
+        Some(syntax.join(&names))
+    }
+}
@@ -1,25 +1,41 @@
 use ::*;
-use std::io::Write;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::io::Read;
-use keys::{Key,CompositeKey};
+use keys::CompositeKey;
+use hmac;
+use stream;
 use byteorder::{LittleEndian, ReadBytesExt, ByteOrder};
 use elementtree::Element;
-use openssl::hash::{Hasher, MessageDigest};
 use openssl::sha;
-use openssl::symm::{self,Cipher};
 use flate2::read::GzDecoder;
 
+type HeaderMap = HashMap<u8, Vec<u8>>;
+
 pub struct Reader {
     key : CompositeKey,
 }
 
+/// Keeps a copy of everything read through it, so the header can be hashed
+/// after it has been parsed.
+struct Recorder<'a> {
+    inner : &'a mut dyn Read,
+    bytes : Vec<u8>,
+}
+
+impl<'a> Read for Recorder<'a> {
+    fn read(&mut self, buf : &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
 const SIG1_N : usize = 4; // I assume I won't need this in future Rust versions.
-const SIG1   : [u8; SIG1_N] = [0x03, 0xD9, 0xA2, 0x9A];
+pub const SIG1 : [u8; SIG1_N] = [0x03, 0xD9, 0xA2, 0x9A];
 
 const SIG2_N : usize = 4;
-const SIG2   : [u8; SIG2_N] = [0x67, 0xFB, 0x4B, 0xB5];
+pub const SIG2 : [u8; SIG2_N] = [0x67, 0xFB, 0x4B, 0xB5];
 
 impl Reader {
     pub fn new(key : CompositeKey) -> Reader {
@@ -59,9 +75,13 @@ impl Reader {
         })
     }
 
-    fn parse_header(&self, r : &mut Read) -> Result<(u8, Vec<u8>), Error> {
+    fn parse_header(&self, r : &mut dyn Read, version : &Version)
+                    -> Result<(u8, Vec<u8>), Error> {
         let id = r.read_u8()?;
-        let sz = r.read_u16::<LittleEndian>()?;
+        let sz = match version.major {
+            3 => r.read_u16::<LittleEndian>()? as u32,
+            _ => r.read_u32::<LittleEndian>()?,
+        };
 
         let mut data = vec![0u8; sz as usize];
         r.read_exact(&mut data)?;
@@ -69,10 +89,15 @@ impl Reader {
         Ok((id, data))
     }
 
-    fn parse_headers(&self, r : &mut Read) -> Result<HashMap<u8, Vec<u8>>, Error> {
+    /// Parses the outer header, returning the fields along with their ids in
+    /// the order they appeared.
+    fn parse_headers(&self, r : &mut dyn Read, version : &Version)
+                     -> Result<(HeaderMap, Vec<u8>), Error> {
         let mut headers : HashMap<u8, Vec<u8>> = HashMap::new();
+        let mut order = Vec::new();
         loop {
-            let (id, bytes) = self.parse_header(r)?;
+            let (id, bytes) = self.parse_header(r, version)?;
+            order.push(id);
 
             // TODO: Support duplicate headers.
             match headers.entry(id) {
@@ -85,7 +110,7 @@ impl Reader {
             }
         }
 
-        Ok(headers)
+        Ok((headers, order))
     }
 
     fn read_u32(v : &Vec<u8>) -> Result<u32, Error> {
@@ -114,6 +139,7 @@ impl Reader {
                          -> Result<OuterCipher, Error> {
         hdrs.remove_hdr(Header::CipherId)
             .ok_or_else(|| Error::new("missing outer cipher"))
+            .and_then(|ref x| Uuid::from_slice(x))
             .and_then(|ref x| OuterCipher::from(x))
     }
 
@@ -165,30 +191,43 @@ impl Reader {
             .ok_or_else(|| Error::new("missing transform seed"))
     }
 
+    /// Takes the headers that aren't modelled, keeping their original order.
+    fn take_other_headers(mut hdrs : HashMap<u8, Vec<u8>>, order : &[u8])
+                          -> Vec<(u8, Vec<u8>)> {
+        order.iter()
+             .filter_map(|id| hdrs.remove(id).map(|x| (*id, x)))
+             .collect()
+    }
+
+    fn take_kdf(hdrs : &mut HashMap<u8, Vec<u8>>)
+                -> Result<(Kdf, Vec<u8>, u64), Error> {
+        hdrs.remove_hdr(Header::KdfParameters)
+            .ok_or_else(|| Error::new("missing kdf parameters"))
+            .and_then(|ref x| VariantDictionary::from_bytes(x))
+            .and_then(|ref x| Kdf::from_parameters(x))
+    }
+
+    fn take_public_custom_data(hdrs : &mut HashMap<u8, Vec<u8>>)
+                               -> Result<VariantDictionary, Error> {
+        match hdrs.remove_hdr(Header::PublicCustomData) {
+            None    => Ok(VariantDictionary::new()),
+            Some(x) => VariantDictionary::from_bytes(&x),
+        }
+    }
+
     fn read_payload(&self,
                     db : &Database,
                     r : &mut Read) -> Result<Element, Error> {
-        println!("original_key: {:?}", self.key.bytes());
-        println!("transform_seed: {:?}", db.transform_seed);
-        let transformed_key = self.key.transform(db.transform_rounds,
-                                                 &db.transform_seed)?;
-
-        println!("transformed_key: {:?}", transformed_key);
-        let mut hasher = Hasher::new(MessageDigest::sha256())?;
-        hasher.write(&db.master_seed)?;
-        hasher.write(&transformed_key)?;
-
-        let master_key = hasher.finish2()?;
-        println!("master_key: {:?}", master_key);
+        let seeds = db.seeds();
+        let master_key = seeds.master_key(&db.transformed_key(&seeds, &self.key)?)?;
 
         let mut ciphertext : Vec<u8> = Vec::new();
 
         r.read_to_end(&mut ciphertext)?;
 
-        let mut plaintext = symm::decrypt(Cipher::aes_256_cbc(),
-                                          &master_key.to_vec(),
-                                          Some(&db.encryption_iv),
-                                          &ciphertext)?;
+        let mut plaintext = db.outer_cipher.decrypt(&master_key,
+                                                    &db.encryption_iv,
+                                                    &ciphertext)?;
 
         let sbytes = &db.stream_start_bytes;
 
@@ -231,12 +270,110 @@ impl Reader {
         }
     }
 
-    pub fn read_from(&self, r : &mut Read) -> Result<Database, Error> {
-        self.parse_sig1(r)?;
-        self.parse_sig2(r)?;
-        let version = self.parse_version(r)?;
-        let mut hdrs = self.parse_headers(r)?;
+    /// Reads the HMAC block stream of a KDBX 4 file, verifying each block.
+    fn read_hmac_blocks(r : &mut dyn Read, hmac_key : &[u8]) -> Result<Vec<u8>, Error> {
+        let mut bytes : Vec<u8> = Vec::new();
+        let mut index = 0u64;
+        loop {
+            let mut mac = [0u8; 32];
+            r.read_exact(&mut mac)?;
+
+            let size = r.read_i32::<LittleEndian>()?;
+            if size < 0 {
+                return Err(Error::new("malformed block size"));
+            }
+
+            let mut data = vec![0u8; size as usize];
+            r.read_exact(&mut data)?;
+
+            if !hmac::verify(&hmac::block_hmac(hmac_key, index, &data)?, &mac) {
+                return Err(Error::new("bad hmac"));
+            }
+
+            if 0 == size {
+                break;
+            }
+
+            bytes.extend_from_slice(&data[..]);
+            index += 1;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Reads the inner header of a KDBX 4 payload, which holds the inner
+    /// stream settings and the attachments.
+    fn read_inner_header(db : &mut Database, r : &mut dyn Read) -> Result<(), Error> {
+        let mut cipher = None;
+        loop {
+            let id = r.read_u8()?;
+            let size = r.read_i32::<LittleEndian>()?;
+            if size < 0 {
+                return Err(Error::new("malformed inner header"));
+            }
+
+            let mut data = vec![0u8; size as usize];
+            r.read_exact(&mut data)?;
+
+            match id {
+                0 => break,
+                1 => cipher = Some(InnerStreamCipher::from(Reader::read_u32(&data)?)?),
+                2 => db.inner_stream_key = Some(data),
+                3 => {
+                    if data.is_empty() {
+                        return Err(Error::new("malformed binary"));
+                    }
+
+                    db.binaries.push(Binary {
+                        protected: 0 != data[0] & 0x01,
+                        data: data[1..].to_vec(),
+                    });
+                },
+                _ => db.other_inner_headers.push((id, data)),
+            }
+        }
+
+        db.inner_stream_cipher = cipher.ok_or_else(|| Error::new("missing inner random stream id"))?;
+        Ok(())
+    }
+
+    fn read_v4(&self, mut db : Database, header : &[u8], r : &mut dyn Read)
+               -> Result<Database, Error> {
+        let mut hash = [0u8; 32];
+        r.read_exact(&mut hash)?;
+        if sha::sha256(header) != hash {
+            return Err(Error::new("header hash mismatch"));
+        }
+
+        let mut mac = [0u8; 32];
+        r.read_exact(&mut mac)?;
+
+        let seeds = db.seeds();
+        let transformed_key = db.transformed_key(&seeds, &self.key)?;
+        let hmac_key = seeds.hmac_key(&transformed_key)?;
+        if !hmac::verify(&hmac::header_hmac(&hmac_key, header)?, &mac) {
+            return Err(Error::new("invalid key or corrupt header"));
+        }
 
+        let ciphertext = Reader::read_hmac_blocks(r, &hmac_key)?;
+        let plaintext = db.outer_cipher.decrypt(&seeds.master_key(&transformed_key)?,
+                                                &db.encryption_iv,
+                                                &ciphertext)?;
+
+        let mut bytes : Vec<u8> = Vec::new();
+        match db.compression {
+            Compression::None => bytes = plaintext,
+            Compression::GZip => { GzDecoder::new(&plaintext[..])?.read_to_end(&mut bytes)?; },
+        }
+
+        let mut data : &[u8] = &bytes;
+        Reader::read_inner_header(&mut db, &mut data)?;
+        db.xml_doc = Element::from_reader(data)?;
+        Ok(db)
+    }
+
+    fn read_v3(&self, version : Version, mut hdrs : HashMap<u8, Vec<u8>>,
+               order : &[u8], r : &mut dyn Read) -> Result<Database, Error> {
         let mut db = Database {
             version             : version,
             compression         : Reader::take_compression(&mut hdrs)?,
@@ -246,14 +383,65 @@ impl Reader {
             inner_stream_cipher : Reader::take_inner_stream_cipher(&mut hdrs)?,
             master_seed         : Reader::take_master_seed(&mut hdrs)?,
             stream_start_bytes  : Reader::take_stream_start_bytes(&mut hdrs),
+            kdf                 : Kdf::Aes,
             transform_rounds    : Reader::take_transform_rounds(&mut hdrs)?,
             transform_seed      : Reader::take_transform_seed(&mut hdrs)?,
-            other_headers       : hdrs.drain().collect(),
-            xml_doc             : Element::new("Banana")
+            other_headers       : Reader::take_other_headers(hdrs, order),
+            public_custom_data  : VariantDictionary::new(),
+            binaries            : vec![],
+            other_inner_headers : vec![],
+            key                 : None,
+            xml_doc             : Element::new("Banana"),
+            xml_version         : version,
         };
 
         db.xml_doc = self.read_payload(&db, r)?;
+        Ok(db)
+    }
+
+    pub fn read_from(&self, r : &mut dyn Read) -> Result<Database, Error> {
+        let (version, mut hdrs, order, header) = {
+            let mut rec = Recorder { inner: r, bytes: vec![] };
+            self.parse_sig1(&mut rec)?;
+            self.parse_sig2(&mut rec)?;
+            let version = self.parse_version(&mut rec)?;
+            let (hdrs, order) = self.parse_headers(&mut rec, &version)?;
+            (version, hdrs, order, rec.bytes)
+        };
+
+        let mut db = match version.major {
+            3 => self.read_v3(version, hdrs, &order, r)?,
+            4 => {
+                let (kdf, seed, rounds) = Reader::take_kdf(&mut hdrs)?;
+                let db = Database {
+                    version,
+                    compression         : Reader::take_compression(&mut hdrs)?,
+                    outer_cipher        : Reader::take_outer_cipher(&mut hdrs)?,
+                    encryption_iv       : Reader::take_encryption_iv(&mut hdrs)?,
+                    inner_stream_key    : None,
+                    inner_stream_cipher : InnerStreamCipher::None,
+                    master_seed         : Reader::take_master_seed(&mut hdrs)?,
+                    stream_start_bytes  : vec![],
+                    kdf,
+                    transform_rounds    : rounds,
+                    transform_seed      : seed,
+                    public_custom_data  : Reader::take_public_custom_data(&mut hdrs)?,
+                    other_headers       : Reader::take_other_headers(hdrs, &order),
+                    binaries            : vec![],
+                    other_inner_headers : vec![],
+                    key                 : None,
+                    xml_doc             : Element::new("Banana"),
+                    xml_version         : version,
+                };
+                self.read_v4(db, &header, r)?
+            },
+            _ => return Err(Error::new("unsupported version")),
+        };
+
+        let mut stream = db.inner_stream(&db.seeds())?;
+        stream::unprotect(&mut db.xml_doc, &mut stream)?;
 
+        db.key = Some(self.key.clone());
         Ok(db)
     }
 }
@@ -0,0 +1,149 @@
+use ::{Database, Entry, Error, Group, Uuid, Version};
+use group;
+use xml;
+
+use elementtree::Element;
+
+/// Builds a recycle bin group with the settings KeePass gives it.
+fn new_recycle_bin(uuid : &Uuid, version : &Version) -> Element {
+    let mut e = group::new_element(uuid, "Recycle Bin", version);
+    xml::set_child_text(&mut e, "IconID", "43");
+    xml::set_child_text(&mut e, "IsExpanded", xml::format_bool(false));
+    xml::set_child_text(&mut e, "EnableAutoType", xml::format_bool(false));
+    xml::set_child_text(&mut e, "EnableSearching", xml::format_bool(false));
+    e
+}
+
+impl Database {
+    /// Returns the recycle bin group, if the database has one.
+    pub fn recycle_bin(&self) -> Option<Group<'_>> {
+        self.meta()
+            .and_then(|x| x.recycle_bin_uuid())
+            .and_then(|x| self.group(&x))
+    }
+
+    fn recycle_bin_enabled(&self) -> bool {
+        self.meta().map(|x| x.recycle_bin_enabled()).unwrap_or(false)
+    }
+
+    /// Returns the recycle bin's UUID, creating the group at the end of the
+    /// root group if it doesn't exist.
+    fn recycle_bin_or_create(&mut self) -> Result<Uuid, Error> {
+        if let Some(uuid) = self.recycle_bin().and_then(|x| x.uuid()) {
+            return Ok(uuid);
+        }
+
+        let uuid = Uuid::new_v4()?;
+        let bin = new_recycle_bin(&uuid, &self.version);
+        self.root_group_elem_mut()?.append_child(bin);
+
+        self.meta_mut()
+            .ok_or_else(|| Error::new("missing meta"))?
+            .set_recycle_bin_uuid(Some(&uuid));
+
+        Ok(uuid)
+    }
+
+    /// Checks whether an item is the recycle bin or somewhere inside it.
+    fn in_recycle_bin(&self, tag : &str, uuid : &Uuid) -> Result<bool, Error> {
+        let path = self.item_path(tag, uuid)?;
+
+        Ok(match self.recycle_bin().and_then(|x| x.uuid()) {
+            None        => false,
+            Some(bin)   => path.contains(&bin),
+        })
+    }
+
+    /// Deletes an entry. If the recycle bin is enabled the entry is moved
+    /// into it, otherwise, or if it is already in the bin, it is removed
+    /// permanently.
+    pub fn delete_entry(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        if !self.recycle_bin_enabled() || self.in_recycle_bin("Entry", uuid)? {
+            return self.remove_entry(uuid);
+        }
+
+        let bin = self.recycle_bin_or_create()?;
+        self.move_entry(uuid, &bin)
+    }
+
+    /// Deletes a group and everything in it. Like KeePass, the group is
+    /// removed permanently instead of being recycled if it is the recycle
+    /// bin, is inside it or contains it.
+    pub fn delete_group(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        if self.item_path("Group", uuid)?.len() < 2 {
+            return Err(Error::new("cannot remove the root group"));
+        }
+
+        let contains_bin = match self.recycle_bin().and_then(|x| x.uuid()) {
+            None        => false,
+            Some(bin)   => self.item_path("Group", &bin)?.contains(uuid),
+        };
+
+        if !self.recycle_bin_enabled() || contains_bin
+            || self.in_recycle_bin("Group", uuid)? {
+            return self.remove_group(uuid);
+        }
+
+        let bin = self.recycle_bin_or_create()?;
+        self.move_group(uuid, &bin)
+    }
+
+    /// Moves an item out of the recycle bin, back into the group it was
+    /// deleted from. The previous group is only recorded by KDBX 4.1, so
+    /// items go to the root group if it is unknown or no longer exists.
+    pub fn restore(&mut self, uuid : &Uuid) -> Result<(), Error> {
+        let tag = match self.entry(uuid) {
+            Some(_) => "Entry",
+            None    => "Group",
+        };
+
+        let bin = self.recycle_bin()
+                      .and_then(|x| x.uuid())
+                      .ok_or_else(|| Error::new("item is not in the recycle bin"))?;
+
+        let path = self.item_path(tag, uuid)?;
+        if !path[..path.len() - 1].contains(&bin) {
+            return Err(Error::new("item is not in the recycle bin"));
+        }
+
+        let previous = match tag {
+            "Entry" => self.entry(uuid).and_then(|x| x.previous_parent_group()),
+            _       => self.group(uuid).and_then(|x| x.previous_parent_group()),
+        };
+
+        let target = match previous {
+            Some(ref x) if self.group(x).is_some()
+                           && !self.in_recycle_bin("Group", x)? => *x,
+            _ => self.root_group()
+                     .and_then(|x| x.uuid())
+                     .ok_or_else(|| Error::new("missing root group"))?,
+        };
+
+        match tag {
+            "Entry" => self.move_entry(uuid, &target),
+            _       => self.move_group(uuid, &target),
+        }
+    }
+
+    /// Permanently removes everything in the recycle bin, recording
+    /// tombstones for the removed items. The bin itself is kept.
+    pub fn empty_recycle_bin(&mut self) -> Result<(), Error> {
+        let (entries, groups) = match self.recycle_bin() {
+            None        => return Ok(()),
+            Some(bin)   => (
+                bin.entries().iter().filter_map(Entry::uuid).collect::<Vec<_>>(),
+                bin.groups().iter().filter_map(Group::uuid).collect::<Vec<_>>(),
+            ),
+        };
+
+        for uuid in entries {
+            self.remove_entry(&uuid)?;
+        }
+
+        for uuid in groups {
+            self.remove_group(&uuid)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use byteorder::{LittleEndian, ByteOrder};
+
+const SIGMA : [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// The Salsa20/20 stream cipher, as used to protect values inside KDBX 3.1
+/// files.
+pub struct Salsa20 {
+    state   : [u32; 16],
+    block   : [u8; 64],
+    pos     : usize,
+}
+
+fn quarter_round(x : &mut [u32; 16], a : usize, b : usize, c : usize, d : usize) {
+    x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+    x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+    x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+    x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+}
+
+impl Salsa20 {
+    pub fn new(key : &[u8], nonce : &[u8]) -> Salsa20 {
+        assert_eq!(key.len(), 32);
+        assert_eq!(nonce.len(), 8);
+
+        let mut state = [0u32; 16];
+        state[0] = SIGMA[0];
+        state[5] = SIGMA[1];
+        state[10] = SIGMA[2];
+        state[15] = SIGMA[3];
+
+        for i in 0..4 {
+            state[1 + i] = LittleEndian::read_u32(&key[i * 4..]);
+            state[11 + i] = LittleEndian::read_u32(&key[16 + i * 4..]);
+        }
+
+        state[6] = LittleEndian::read_u32(&nonce[0..]);
+        state[7] = LittleEndian::read_u32(&nonce[4..]);
+
+        Salsa20 {
+            state,
+            block: [0u8; 64],
+            pos: 64,
+        }
+    }
+
+    fn next_block(&mut self) {
+        let mut x = self.state;
+
+        for _ in 0..10 {
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 5, 9, 13, 1);
+            quarter_round(&mut x, 10, 14, 2, 6);
+            quarter_round(&mut x, 15, 3, 7, 11);
+
+            quarter_round(&mut x, 0, 1, 2, 3);
+            quarter_round(&mut x, 5, 6, 7, 4);
+            quarter_round(&mut x, 10, 11, 8, 9);
+            quarter_round(&mut x, 15, 12, 13, 14);
+        }
+
+        for (i, word) in x.iter().enumerate() {
+            let word = word.wrapping_add(self.state[i]);
+            LittleEndian::write_u32(&mut self.block[i * 4..], word);
+        }
+
+        self.state[8] = self.state[8].wrapping_add(1);
+        if 0 == self.state[8] {
+            self.state[9] = self.state[9].wrapping_add(1);
+        }
+
+        self.pos = 0;
+    }
+
+    /// XORs the next bytes of the key stream into `data`.
+    pub fn apply(&mut self, data : &mut [u8]) {
+        for byte in data.iter_mut() {
+            if 64 == self.pos {
+                self.next_block();
+            }
+
+            *byte ^= self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex::FromHex;
+
+    /// eSTREAM, Salsa20/20 with a 256 bit key, set 1, vector 0.
+    #[test]
+    fn estream_set1_vector0() {
+        let mut key = [0u8; 32];
+        key[0] = 0x80;
+        let expected = Vec::from_hex("E3BE8FDD8BECA2E3EA8EF9475B29A6E7\
+                                      003951E1097A5C38D23B7A5FAD9F6844\
+                                      B22C97559E2723C7CBBD3FE4FC8D9A07\
+                                      44652A83E72A9C461876AF4D7EF1A117").unwrap();
+
+        let mut data = [0u8; 64];
+        Salsa20::new(&key, &[0u8; 8]).apply(&mut data);
+        assert_eq!(&data[..], &expected[..]);
+    }
+}
@@ -0,0 +1,165 @@
+use ::Error;
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDateTime, Utc};
+use hex::ToHex;
+use openssl::rand;
+
+/// Builds a path next to `path`, with `prefix` and `suffix` added to its file
+/// name.
+fn sibling(path : &Path, prefix : &str, suffix : &str) -> Result<PathBuf, Error> {
+    let name = path.file_name()
+                   .ok_or_else(|| Error::new("path has no file name"))?;
+
+    let mut sibling = OsString::from(prefix);
+    sibling.push(name);
+    sibling.push(suffix);
+    Ok(path.with_file_name(sibling))
+}
+
+fn directory(path : &Path) -> &Path {
+    match path.parent() {
+        Some(x) if !x.as_os_str().is_empty()    => x,
+        _                                       => Path::new("."),
+    }
+}
+
+/// Flushes the directory entry for a rename to disk. Only Unix lets
+/// directories be opened and synced like files.
+#[cfg(unix)]
+fn sync_directory(dir : &Path) -> Result<(), Error> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_dir : &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+/// The timestamp format of backup files, which sorts in the order the
+/// backups were made.
+const BACKUP_STAMP : &str = "%Y%m%dT%H%M%S%.6fZ";
+
+/// Copies the current file to a timestamped backup next to it, named like
+/// `vault.kdbx.20170102T030405.000000Z.bak`.
+fn backup(path : &Path) -> Result<PathBuf, Error> {
+    let stamp = Utc::now().format(BACKUP_STAMP).to_string();
+    let backup = sibling(path, "", &format!(".{}.bak", stamp))?;
+
+    fs::copy(path, &backup)?;
+    File::open(&backup)?.sync_all()?;
+    Ok(backup)
+}
+
+/// Removes the oldest backups of `path`, so only `keep` remain.
+fn prune_backups(path : &Path, keep : usize) -> Result<(), Error> {
+    let prefix = format!("{}.", path.file_name().unwrap().to_string_lossy());
+
+    let mut backups = Vec::new();
+    for item in fs::read_dir(directory(path))? {
+        let name = item?.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(&prefix) || !name.ends_with(".bak")
+            || name.len() < prefix.len() + 4 {
+            continue;
+        }
+
+        let stamp = &name[prefix.len()..name.len() - 4];
+        if NaiveDateTime::parse_from_str(stamp, BACKUP_STAMP).is_ok() {
+            backups.push(name);
+        }
+    }
+
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        fs::remove_file(path.with_file_name(old))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a file to a temporary file in the same directory, syncs it, checks
+/// it with `verify` and then renames it over `path`, so a crash never leaves
+/// a partially written file behind.
+///
+/// If `backups` is not zero, the previous file is copied to a timestamped
+/// backup before the rename. Old backups are only removed, keeping at most
+/// `backups`, once the rename has succeeded; if it fails, the new backup is
+/// removed again.
+pub fn replace<W, V>(path : &Path, backups : usize, write : W, verify : V)
+                     -> Result<(), Error>
+    where W : FnOnce(&mut dyn Write) -> Result<(), Error>,
+          V : FnOnce(&mut File) -> Result<(), Error>,
+{
+    let mut random = [0u8; 8];
+    rand::rand_bytes(&mut random)?;
+    let temp = sibling(path, ".", &format!(".{}.tmp", random.to_hex()))?;
+
+    let result = write_temp(&temp, write)
+        .and_then(|_| verify(&mut File::open(&temp)?))
+        .and_then(|_| if 0 < backups && path.is_file() {
+            backup(path).map(Some)
+        } else {
+            Ok(None)
+        })
+        .and_then(|backup| match fs::rename(&temp, path) {
+            Ok(_)   => Ok(backup),
+            Err(e)  => {
+                if let Some(backup) = backup {
+                    let _ = fs::remove_file(backup);
+                }
+                Err(Error::from(e))
+            },
+        });
+
+    let backup = match result {
+        Ok(backup)  => backup,
+        Err(e)      => {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        },
+    };
+
+    sync_directory(directory(path))?;
+
+    // The save has already happened, so failing to clean up old backups
+    // is not worth reporting as a failed save.
+    if backup.is_some() {
+        let _ = prune_backups(path, backups);
+    }
+
+    Ok(())
+}
+
+/// Creates the temporary file. On Unix it is only readable by its owner, as
+/// it ends up replacing the database.
+#[cfg(unix)]
+fn create_temp(temp : &Path) -> Result<File, Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    Ok(OpenOptions::new().write(true).create_new(true).mode(0o600).open(temp)?)
+}
+
+#[cfg(not(unix))]
+fn create_temp(temp : &Path) -> Result<File, Error> {
+    Ok(OpenOptions::new().write(true).create_new(true).open(temp)?)
+}
+
+fn write_temp<W>(temp : &Path, write : W) -> Result<(), Error>
+    where W : FnOnce(&mut dyn Write) -> Result<(), Error>
+{
+    let file = create_temp(temp)?;
+
+    let mut w = BufWriter::new(file);
+    write(&mut w)?;
+
+    let file = w.into_inner()
+                .map_err(|_| Error::new("error flushing temporary file"))?;
+    file.sync_all()?;
+    Ok(())
+}
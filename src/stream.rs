@@ -0,0 +1,103 @@
+use ::{Error, InnerStreamCipher};
+use chacha20::ChaCha20;
+use salsa20::Salsa20;
+
+use base64;
+use elementtree::Element;
+use openssl::sha;
+
+const SALSA20_IV : [u8; 8] = [0xE8, 0x30, 0x09, 0x4B, 0x97, 0x20, 0x5D, 0x2A];
+
+enum Cipher {
+    Salsa20(Salsa20),
+    ChaCha20(ChaCha20),
+}
+
+/// The key stream used to protect individual values inside the XML payload.
+pub struct InnerStream {
+    cipher : Option<Cipher>,
+}
+
+impl InnerStream {
+    pub fn new(cipher : &InnerStreamCipher, key : &[u8]) -> InnerStream {
+        let cipher = match *cipher {
+            InnerStreamCipher::None     => None,
+            InnerStreamCipher::Salsa20  => Some(Cipher::Salsa20(
+                Salsa20::new(&sha::sha256(key), &SALSA20_IV))),
+            InnerStreamCipher::ChaCha20 => {
+                let hash = sha::sha512(key);
+                Some(Cipher::ChaCha20(ChaCha20::new(&hash[..32], &hash[32..44])))
+            },
+        };
+
+        InnerStream {
+            cipher,
+        }
+    }
+
+    pub fn apply(&mut self, data : &mut [u8]) -> Result<(), Error> {
+        match self.cipher {
+            None                                => Ok(()),
+            Some(Cipher::Salsa20(ref mut x))    => {
+                x.apply(data);
+                Ok(())
+            },
+            Some(Cipher::ChaCha20(ref mut x))   => x.apply(data),
+        }
+    }
+}
+
+fn is_protected(e : &Element) -> bool {
+    e.get_attr("Protected")
+     .map(|x| x.eq_ignore_ascii_case("true"))
+     .unwrap_or(false)
+}
+
+/// Decrypts every protected value in document order, leaving plaintext in
+/// its place. The `Protected` attribute is kept so the value can be
+/// protected again when the database is written.
+///
+/// Binaries stay base64 encoded, since their contents need not be text.
+pub fn unprotect(e : &mut Element, stream : &mut InnerStream) -> Result<(), Error> {
+    if is_protected(e) {
+        let mut data = base64::decode(e.text().trim())
+                       .map_err(|_| Error::new("malformed protected value"))?;
+        stream.apply(&mut data)?;
+
+        if e.tag().name() == "Binary" {
+            e.set_text(base64::encode(&data));
+        } else {
+            let text = String::from_utf8(data)
+                       .map_err(|_| Error::new("protected value is not UTF-8"))?;
+            e.set_text(text);
+        }
+    }
+
+    for child in e.children_mut() {
+        unprotect(child, stream)?;
+    }
+
+    Ok(())
+}
+
+/// The inverse of `unprotect`: encrypts every protected value in document
+/// order and replaces it with the base64 encoded ciphertext.
+pub fn protect(e : &mut Element, stream : &mut InnerStream) -> Result<(), Error> {
+    if is_protected(e) {
+        let mut data = if e.tag().name() == "Binary" {
+            base64::decode(e.text().trim())
+                .map_err(|_| Error::new("malformed binary"))?
+        } else {
+            e.text().as_bytes().to_vec()
+        };
+
+        stream.apply(&mut data)?;
+        e.set_text(base64::encode(&data));
+    }
+
+    for child in e.children_mut() {
+        protect(child, stream)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use ::{Database, Entry, EntryMut, Error, Times, Uuid};
+use entry::ENTRY_ORDER;
+use xml;
+
+use chrono::Utc;
+
+impl Database {
+    /// Returns the entries in the templates group set by
+    /// `EntryTemplatesGroup`, including those in its subgroups.
+    pub fn entry_templates(&self) -> Vec<Entry<'_>> {
+        self.meta()
+            .and_then(|x| x.entry_templates_group())
+            .and_then(|x| self.group(&x))
+            .map(|x| x.all_entries())
+            .unwrap_or(vec![])
+    }
+
+    /// Creates a new entry in `parent` from a template, returning its UUID.
+    /// Like KeePass, the copy keeps the template's fields, including custom
+    /// ones, but gets a fresh UUID, an empty title, new times and no history.
+    pub fn create_entry_from_template(&mut self, template : &Uuid, parent : &Uuid)
+                                      -> Result<Uuid, Error> {
+        let templates = self.meta()
+                            .and_then(|x| x.entry_templates_group())
+                            .ok_or_else(|| Error::new("no entry templates group"))?;
+
+        let mut elem = match self.find_item("Entry", template) {
+            Some(ref chain) if chain.iter()
+                                    .any(|x| xml::item_uuid(x) == Some(templates))
+                => chain[chain.len() - 1].clone(),
+            _   => return Err(Error::new("template not found")),
+        };
+
+        if self.group(parent).is_none() {
+            return Err(Error::new("group not found"));
+        }
+
+        let uuid = Uuid::new_v4()?;
+        let version = self.version;
+
+        while let Some(idx) = elem.children()
+                                  .position(|x| x.tag().name() == "History"
+                                                || x.tag().name() == "PreviousParentGroup") {
+            elem.remove_child(idx);
+        }
+
+        xml::set_child_text(&mut elem, "UUID", uuid.to_base64());
+        Times::new(Utc::now())
+            .write_xml(xml::ordered_child(&mut elem, "Times", ENTRY_ORDER), &version);
+        xml::ordered_child(&mut elem, "History", ENTRY_ORDER);
+
+        EntryMut::new(&mut elem, version).set_field("Title", "");
+
+        let dest = self.root_group_elem_mut()
+                       .map(|x| xml::find_item_mut(x, "Group", parent))?
+                       .unwrap();
+        xml::append_item(dest, elem);
+
+        Ok(uuid)
+    }
+}
@@ -0,0 +1,181 @@
+use ::{Database, Entry, Error, Version};
+use xml;
+
+use base64;
+use byteorder::{LittleEndian, ByteOrder};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use elementtree::Element;
+
+/// Seconds between 0001-01-01T00:00:00Z, the KDBX 4 epoch, and the Unix epoch.
+const EPOCH_OFFSET : i64 = 62135596800;
+
+/// Parses a timestamp in either the ISO-8601 form written by KDBX 3.1 or the
+/// base64 encoded seconds written by KDBX 4.
+///
+/// ISO timestamps may carry any RFC 3339 offset. Ones without an offset are
+/// taken to be UTC, which is what KeePass writes.
+pub fn parse_time(s : &str) -> Result<DateTime<Utc>, Error> {
+    let s = s.trim();
+
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+
+    if let Ok(t) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(Utc.from_utc_datetime(&t));
+    }
+
+    let bytes = base64::decode(s)
+                .map_err(|_| Error::new("malformed timestamp"))?;
+
+    if 8 != bytes.len() {
+        return Err(Error::new("malformed timestamp"));
+    }
+
+    let secs = LittleEndian::read_i64(&bytes)
+                   .checked_sub(EPOCH_OFFSET)
+                   .ok_or_else(|| Error::new("timestamp out of range"))?;
+    Utc.timestamp_opt(secs, 0)
+       .single()
+       .ok_or_else(|| Error::new("timestamp out of range"))
+}
+
+/// Formats a timestamp the way the given file format version expects.
+pub fn format_time(t : &DateTime<Utc>, version : &Version) -> String {
+    if version.major >= 4 {
+        let mut bytes = [0u8; 8];
+        LittleEndian::write_i64(&mut bytes, t.timestamp() + EPOCH_OFFSET);
+        base64::encode(&bytes)
+    } else {
+        t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+}
+
+/// The `<Times>` block carried by every group and entry. Timestamps missing
+/// from the XML are `None`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Times {
+    pub creation_time           : Option<DateTime<Utc>>,
+    pub last_modification_time  : Option<DateTime<Utc>>,
+    pub last_access_time        : Option<DateTime<Utc>>,
+    pub expiry_time             : Option<DateTime<Utc>>,
+    pub expires                 : bool,
+    pub usage_count             : u64,
+    pub location_changed        : Option<DateTime<Utc>>,
+}
+
+impl Times {
+    /// Creates a block with every timestamp set to `now`, as KeePass does for
+    /// newly created items.
+    pub fn new(now : DateTime<Utc>) -> Times {
+        Times {
+            creation_time           : Some(now),
+            last_modification_time  : Some(now),
+            last_access_time        : Some(now),
+            expiry_time             : Some(now),
+            expires                 : false,
+            usage_count             : 0,
+            location_changed        : Some(now),
+        }
+    }
+
+    fn read_time(e : &Element, tag : &str) -> Result<Option<DateTime<Utc>>, Error> {
+        match xml::child_text(e, tag) {
+            None    => Ok(None),
+            Some(x) => parse_time(x).map(Some),
+        }
+    }
+
+    fn write_time(e : &mut Element, tag : &str, t : &Option<DateTime<Utc>>,
+                  version : &Version) {
+        if let Some(ref t) = *t {
+            xml::set_child_text(e, tag, format_time(t, version));
+        }
+    }
+
+    /// Reads a `<Times>` element. Missing timestamps are `None`.
+    pub fn from_xml(e : &Element) -> Result<Times, Error> {
+        let expires = match xml::child_text(e, "Expires") {
+            None    => false,
+            Some(x) => xml::parse_bool(x)
+                       .ok_or_else(|| Error::new("malformed expires flag"))?,
+        };
+
+        let usage_count = match xml::child_text(e, "UsageCount") {
+            None    => 0,
+            Some(x) => x.trim().parse()
+                       .map_err(|_| Error::new("malformed usage count"))?,
+        };
+
+        Ok(Times {
+            creation_time           : Times::read_time(e, "CreationTime")?,
+            last_modification_time  : Times::read_time(e, "LastModificationTime")?,
+            last_access_time        : Times::read_time(e, "LastAccessTime")?,
+            expiry_time             : Times::read_time(e, "ExpiryTime")?,
+            expires,
+            usage_count,
+            location_changed        : Times::read_time(e, "LocationChanged")?,
+        })
+    }
+
+    /// Writes the fields into a `<Times>` element, using the timestamp
+    /// encoding of `version`. Children that are already present are updated
+    /// in place, and timestamps that are `None` are left out.
+    pub fn write_xml(&self, e : &mut Element, version : &Version) {
+        Times::write_time(e, "CreationTime", &self.creation_time, version);
+        Times::write_time(e, "LastModificationTime",
+                          &self.last_modification_time, version);
+        Times::write_time(e, "LastAccessTime", &self.last_access_time, version);
+        Times::write_time(e, "ExpiryTime", &self.expiry_time, version);
+        xml::set_child_text(e, "Expires", xml::format_bool(self.expires));
+        xml::set_child_text(e, "UsageCount", self.usage_count.to_string());
+        Times::write_time(e, "LocationChanged", &self.location_changed, version);
+    }
+
+    /// Records that the item was used at `now`, and also modified if
+    /// `modified` is set. Mirrors KeePass, which counts edits as uses too.
+    pub fn touch(&mut self, now : DateTime<Utc>, modified : bool) {
+        self.last_access_time = Some(now);
+        self.usage_count += 1;
+
+        if modified {
+            self.last_modification_time = Some(now);
+        }
+    }
+
+    /// An item only expires if it has both `Expires` set and an expiry time.
+    pub fn is_expired_at(&self, at : &DateTime<Utc>) -> bool {
+        match self.expiry_time {
+            Some(t) => self.expires && t <= *at,
+            None    => false,
+        }
+    }
+}
+
+impl Database {
+    /// Returns every entry that has expired as of `at`. Entries whose times
+    /// can't be read are skipped; `Entry::times` reports why.
+    pub fn entries_expired_at(&self, at : &DateTime<Utc>) -> Vec<Entry<'_>> {
+        self.entries()
+            .into_iter()
+            .filter(|x| x.times().map(|t| t.is_expired_at(at)).unwrap_or(false))
+            .collect()
+    }
+
+    /// Returns every entry that is not yet expired at `from`, but will be
+    /// within the given window. Like `entries_expired_at`, entries whose
+    /// times can't be read are skipped.
+    pub fn entries_expiring_within(&self, from : &DateTime<Utc>, within : Duration)
+                                   -> Vec<Entry<'_>> {
+        let until = *from + within;
+
+        self.entries()
+            .into_iter()
+            .filter(|x| match x.times() {
+                Ok(t)   => !t.is_expired_at(from) && t.is_expired_at(&until),
+                Err(_)  => false,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,96 @@
+use ::Error;
+
+use std::fmt;
+
+use base64;
+use hex::{FromHex,ToHex};
+use openssl::rand;
+
+/// A 16 byte identifier, as used for groups, entries, icons and the cipher
+/// ids in the header. KeePass stores these base64 encoded in the XML.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Default)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    /// The all-zero UUID, which KeePass uses to mean "none".
+    pub fn nil() -> Uuid {
+        Uuid([0u8; 16])
+    }
+
+    /// Generates a random (version 4) UUID.
+    pub fn new_v4() -> Result<Uuid, Error> {
+        let mut bytes = [0u8; 16];
+        rand::rand_bytes(&mut bytes)?;
+
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        Ok(Uuid(bytes))
+    }
+
+    pub fn from_slice(v : &[u8]) -> Result<Uuid, Error> {
+        if 16 != v.len() {
+            return Err(Error::new("invalid uuid length"));
+        }
+
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(v);
+        Ok(Uuid(bytes))
+    }
+
+    pub fn from_base64(s : &str) -> Result<Uuid, Error> {
+        base64::decode(s.trim())
+            .map_err(|_| Error::new("malformed base64 uuid"))
+            .and_then(|x| Uuid::from_slice(&x))
+    }
+
+    /// Parses 32 hex digits, optionally separated by dashes.
+    pub fn from_hex(s : &str) -> Result<Uuid, Error> {
+        let digits : String = s.trim().chars().filter(|x| *x != '-').collect();
+        let bytes : Vec<u8> = Vec::from_hex(digits)?;
+        Uuid::from_slice(&bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0u8; 16]
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.0)
+    }
+
+    /// Formats the UUID as 32 lowercase hex digits, without dashes.
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+}
+
+/// Formats the UUID in the usual dashed form, e.g.
+/// `31c1f2e6-bf71-4350-be58-05216afc5aff`.
+impl fmt::Display for Uuid {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let hex = self.to_hex();
+        write!(f, "{}-{}-{}-{}-{}",
+               &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+    }
+}
+
+/// Serialized in the dashed form, like `Display`.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Uuid {
+    fn serialize<S : ::serde::Serializer>(&self, s : S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Uuid {
+    fn deserialize<D : ::serde::Deserializer<'de>>(d : D) -> Result<Uuid, D::Error> {
+        let s = String::deserialize(d)?;
+        Uuid::from_hex(&s).map_err(|_| ::serde::de::Error::custom("malformed uuid"))
+    }
+}
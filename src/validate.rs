@@ -0,0 +1,120 @@
+use ::{AutoType, CustomData, DeletedObject, Error, Times, Uuid};
+use xml;
+
+use std::collections::HashSet;
+
+use elementtree::Element;
+
+fn count(e : &Element, tag : &str) -> usize {
+    e.children().filter(|x| x.tag().name() == tag).count()
+}
+
+fn check_uuid(e : &Element) -> Result<Uuid, Error> {
+    xml::item_uuid(e)
+        .ok_or_else(|| Error::new("invalid XML: missing or malformed UUID"))
+}
+
+fn check_times(e : &Element) -> Result<(), Error> {
+    match e.find("Times") {
+        None    => Ok(()),
+        Some(x) => Times::from_xml(x).map(|_| ()),
+    }
+}
+
+fn check_custom_data(e : &Element) -> Result<(), Error> {
+    match e.find("CustomData") {
+        None    => Ok(()),
+        Some(x) => CustomData::from_xml(x).map(|_| ()),
+    }
+}
+
+fn check_entry(e : &Element) -> Result<(), Error> {
+    check_uuid(e)?;
+    check_times(e)?;
+    check_custom_data(e)?;
+
+    if let Some(x) = e.find("AutoType") {
+        AutoType::from_xml(x)?;
+    }
+
+    let mut keys = HashSet::new();
+    for field in e.find_all("String") {
+        if 1 != count(field, "Key") || 1 != count(field, "Value") {
+            return Err(Error::new("invalid XML: string field needs one key and one value"));
+        }
+
+        if !keys.insert(xml::child_text(field, "Key").unwrap()) {
+            return Err(Error::new("invalid XML: duplicate string field"));
+        }
+    }
+
+    if let Some(history) = e.find("History") {
+        for old in history.find_all("Entry") {
+            check_entry(old)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_group(e : &Element, seen : &mut HashSet<Uuid>) -> Result<(), Error> {
+    if !seen.insert(check_uuid(e)?) {
+        return Err(Error::new("invalid XML: duplicate UUID"));
+    }
+
+    check_times(e)?;
+    check_custom_data(e)?;
+
+    for entry in e.find_all("Entry") {
+        check_entry(entry)?;
+
+        if !seen.insert(check_uuid(entry)?) {
+            return Err(Error::new("invalid XML: duplicate UUID"));
+        }
+    }
+
+    for group in e.find_all("Group") {
+        check_group(group, seen)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that a document has the structure KeePass expects: a single
+/// `Meta` and `Root`, a single root group, well-formed UUIDs that are unique
+/// across groups and entries, and parseable times and tombstones.
+pub fn validate(doc : &Element) -> Result<(), Error> {
+    if doc.tag().name() != "KeePassFile" {
+        return Err(Error::new("invalid XML: root element must be KeePassFile"));
+    }
+
+    if 1 != count(doc, "Meta") {
+        return Err(Error::new("invalid XML: expected exactly one Meta"));
+    }
+
+    if 1 != count(doc, "Root") {
+        return Err(Error::new("invalid XML: expected exactly one Root"));
+    }
+
+    check_custom_data(doc.find("Meta").unwrap())?;
+
+    let root = doc.find("Root").unwrap();
+    if 1 != count(root, "Group") {
+        return Err(Error::new("invalid XML: expected exactly one root group"));
+    }
+
+    if count(root, "DeletedObjects") > 1 {
+        return Err(Error::new("invalid XML: expected at most one DeletedObjects"));
+    }
+
+    let mut seen = HashSet::new();
+    check_group(root.find("Group").unwrap(), &mut seen)?;
+
+    if let Some(deleted) = root.find("DeletedObjects") {
+        for obj in deleted.find_all("DeletedObject") {
+            DeletedObject::from_xml(obj)?;
+        }
+    }
+
+    Ok(())
+}
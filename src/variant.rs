@@ -0,0 +1,193 @@
+use ::Error;
+
+use std::io::{Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+const VERSION : u16 = 0x0100;
+const VERSION_CRITICAL_MASK : u16 = 0xFF00;
+
+/// A value stored in a `VariantDictionary`.
+#[derive(Debug,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Variant {
+    UInt32(u32),
+    UInt64(u64),
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl Variant {
+    fn type_id(&self) -> u8 {
+        match *self {
+            Variant::UInt32(_)  => 0x04,
+            Variant::UInt64(_)  => 0x05,
+            Variant::Bool(_)    => 0x08,
+            Variant::Int32(_)   => 0x0C,
+            Variant::Int64(_)   => 0x0D,
+            Variant::String(_)  => 0x18,
+            Variant::Bytes(_)   => 0x42,
+        }
+    }
+
+    fn from_bytes(type_id : u8, mut data : &[u8]) -> Result<Variant, Error> {
+        let malformed = |_| Error::new("malformed variant dictionary value");
+
+        let value = match (type_id, data.len()) {
+            (0x04, 4) => Variant::UInt32(data.read_u32::<LittleEndian>().map_err(malformed)?),
+            (0x05, 8) => Variant::UInt64(data.read_u64::<LittleEndian>().map_err(malformed)?),
+            (0x08, 1) => Variant::Bool(0 != data[0]),
+            (0x0C, 4) => Variant::Int32(data.read_i32::<LittleEndian>().map_err(malformed)?),
+            (0x0D, 8) => Variant::Int64(data.read_i64::<LittleEndian>().map_err(malformed)?),
+            (0x18, _) => Variant::String(String::from_utf8(data.to_vec())
+                             .map_err(|_| Error::new("variant dictionary string is not UTF-8"))?),
+            (0x42, _) => Variant::Bytes(data.to_vec()),
+            _         => return Err(Error::new("malformed variant dictionary value")),
+        };
+
+        Ok(value)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        // Writing to a Vec can't fail.
+        match *self {
+            Variant::UInt32(x)      => out.write_u32::<LittleEndian>(x).unwrap(),
+            Variant::UInt64(x)      => out.write_u64::<LittleEndian>(x).unwrap(),
+            Variant::Bool(x)        => out.push(x as u8),
+            Variant::Int32(x)       => out.write_i32::<LittleEndian>(x).unwrap(),
+            Variant::Int64(x)       => out.write_i64::<LittleEndian>(x).unwrap(),
+            Variant::String(ref x)  => out.extend_from_slice(x.as_bytes()),
+            Variant::Bytes(ref x)   => out.extend_from_slice(x),
+        }
+
+        out
+    }
+}
+
+/// The typed key/value store KDBX 4 uses for the KDF parameters and the
+/// public custom data in the header. Items keep their original order.
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VariantDictionary {
+    items : Vec<(String, Variant)>,
+}
+
+impl VariantDictionary {
+    pub fn new() -> VariantDictionary {
+        VariantDictionary {
+            items: vec![],
+        }
+    }
+
+    pub fn from_bytes(mut r : &[u8]) -> Result<VariantDictionary, Error> {
+        let version = r.read_u16::<LittleEndian>()?;
+        if version & VERSION_CRITICAL_MASK > VERSION & VERSION_CRITICAL_MASK {
+            return Err(Error::new("unsupported variant dictionary version"));
+        }
+
+        let mut dict = VariantDictionary::new();
+        loop {
+            let type_id = r.read_u8()?;
+            if 0 == type_id {
+                break;
+            }
+
+            let key_len = r.read_i32::<LittleEndian>()?;
+            if key_len < 0 {
+                return Err(Error::new("malformed variant dictionary key"));
+            }
+            let mut key = vec![0u8; key_len as usize];
+            r.read_exact(&mut key)?;
+            let key = String::from_utf8(key)
+                      .map_err(|_| Error::new("variant dictionary key is not UTF-8"))?;
+
+            let value_len = r.read_i32::<LittleEndian>()?;
+            if value_len < 0 {
+                return Err(Error::new("malformed variant dictionary value"));
+            }
+            let mut value = vec![0u8; value_len as usize];
+            r.read_exact(&mut value)?;
+
+            dict.items.push((key, Variant::from_bytes(type_id, &value)?));
+        }
+
+        Ok(dict)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        // Writing to a Vec can't fail.
+        out.write_u16::<LittleEndian>(VERSION).unwrap();
+        for (key, value) in &self.items {
+            let data = value.to_bytes();
+
+            out.push(value.type_id());
+            out.write_i32::<LittleEndian>(key.len() as i32).unwrap();
+            out.write_all(key.as_bytes()).unwrap();
+            out.write_i32::<LittleEndian>(data.len() as i32).unwrap();
+            out.write_all(&data).unwrap();
+        }
+        out.push(0);
+
+        out
+    }
+
+    pub fn get(&self, key : &str) -> Option<&Variant> {
+        self.items.iter().find(|x| x.0 == key).map(|x| &x.1)
+    }
+
+    pub fn get_u32(&self, key : &str) -> Option<u32> {
+        match self.get(key) {
+            Some(&Variant::UInt32(x))   => Some(x),
+            _                           => None,
+        }
+    }
+
+    pub fn get_u64(&self, key : &str) -> Option<u64> {
+        match self.get(key) {
+            Some(&Variant::UInt64(x))   => Some(x),
+            _                           => None,
+        }
+    }
+
+    pub fn get_bytes(&self, key : &str) -> Option<&[u8]> {
+        match self.get(key) {
+            Some(Variant::Bytes(x))     => Some(x),
+            _                           => None,
+        }
+    }
+
+    /// Sets the value for `key`, keeping its position if it already exists.
+    pub fn set<K : Into<String>>(&mut self, key : K, value : Variant) {
+        let key = key.into();
+
+        match self.items.iter_mut().find(|x| x.0 == key) {
+            Some(item)  => item.1 = value,
+            None        => self.items.push((key, value)),
+        }
+    }
+
+    pub fn remove(&mut self, key : &str) -> Option<Variant> {
+        self.items.iter()
+                  .position(|x| x.0 == key)
+                  .map(|x| self.items.remove(x).1)
+    }
+
+    pub fn items(&self) -> &[(String, Variant)] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
@@ -0,0 +1,378 @@
+use ::*;
+use std::io::Write;
+use std::path::Path;
+use keys::CompositeKey;
+use convert;
+use hmac;
+use meta::META_ORDER;
+use reader::{SIG1, SIG2};
+use save;
+use stream;
+use byteorder::{LittleEndian, WriteBytesExt, ByteOrder};
+use flate2;
+use flate2::write::GzEncoder;
+use openssl::sha;
+
+/// Writes databases in the KDBX 3.1 and KDBX 4 formats, depending on their
+/// `version`.
+///
+/// Like KeePass, every write uses fresh seeds, IVs and inner stream keys, so
+/// the values in the `Database` are never reused.
+pub struct Writer {
+    key         : CompositeKey,
+    fixed_seeds : bool,
+}
+
+/// The size of the blocks in the hashed block stream, as used by KeePass.
+const BLOCK_SIZE : usize = 1024 * 1024;
+
+/// The data KeePass stores in the `End` header.
+const END_OF_HEADER : [u8; 4] = [0x0D, 0x0A, 0x0D, 0x0A];
+
+impl Writer {
+    pub fn new(key : CompositeKey) -> Writer {
+        Writer {
+            key,
+            fixed_seeds: false,
+        }
+    }
+
+    /// Makes the writer reuse the seeds and IVs stored in the database
+    /// instead of generating new ones, so the output is deterministic.
+    ///
+    /// This is only meant for test fixtures: reusing an IV or inner stream
+    /// key with different contents weakens the encryption.
+    pub fn with_fixed_seeds(mut self) -> Writer {
+        self.fixed_seeds = true;
+        self
+    }
+
+    /// Writes one header field. KDBX 3.1 stores the size in 16 bits, KDBX 4
+    /// in 32.
+    fn write_header(w : &mut Vec<u8>, version : &Version, id : u8, data : &[u8])
+                    -> Result<(), Error> {
+        w.write_u8(id)?;
+
+        if 3 == version.major {
+            if data.len() > u16::MAX as usize {
+                return Err(Error::new("header too large"));
+            }
+            w.write_u16::<LittleEndian>(data.len() as u16)?;
+        } else {
+            if data.len() > i32::MAX as usize {
+                return Err(Error::new("header too large"));
+            }
+            w.write_u32::<LittleEndian>(data.len() as u32)?;
+        }
+
+        w.write_all(data)?;
+        Ok(())
+    }
+
+    /// Serializes everything up to and including the `End` header.
+    fn write_headers(db : &Database, seeds : &Seeds) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        out.write_all(&SIG1)?;
+        out.write_all(&SIG2)?;
+        out.write_u16::<LittleEndian>(db.version.minor)?;
+        out.write_u16::<LittleEndian>(db.version.major)?;
+
+        let version = &db.version;
+
+        let mut compression = [0u8; 4];
+        LittleEndian::write_u32(&mut compression, db.compression.id());
+
+        Writer::write_header(&mut out, version, Header::CipherId as u8,
+                             db.outer_cipher.uuid().as_bytes())?;
+        Writer::write_header(&mut out, version, Header::CompressionFlags as u8,
+                             &compression)?;
+        Writer::write_header(&mut out, version, Header::MasterSeed as u8,
+                             &seeds.master_seed)?;
+
+        if 3 == version.major {
+            let mut rounds = [0u8; 8];
+            LittleEndian::write_u64(&mut rounds, db.transform_rounds);
+
+            let mut stream_id = [0u8; 4];
+            LittleEndian::write_u32(&mut stream_id, db.inner_stream_cipher.id());
+
+            Writer::write_header(&mut out, version, Header::TransformSeed as u8,
+                                 &seeds.transform_seed)?;
+            Writer::write_header(&mut out, version, Header::TransformRounds as u8,
+                                 &rounds)?;
+            Writer::write_header(&mut out, version, Header::EncryptionIv as u8,
+                                 &seeds.encryption_iv)?;
+
+            if let Some(ref key) = seeds.inner_stream_key {
+                Writer::write_header(&mut out, version,
+                                     Header::ProtectedStreamKey as u8, key)?;
+            }
+
+            Writer::write_header(&mut out, version, Header::StreamStartBytes as u8,
+                                 &seeds.stream_start_bytes)?;
+            Writer::write_header(&mut out, version, Header::InnerRandomStreamId as u8,
+                                 &stream_id)?;
+        } else {
+            let params = db.kdf.to_parameters(&seeds.transform_seed, db.transform_rounds);
+
+            Writer::write_header(&mut out, version, Header::EncryptionIv as u8,
+                                 &seeds.encryption_iv)?;
+            Writer::write_header(&mut out, version, Header::KdfParameters as u8,
+                                 &params.to_bytes())?;
+
+            if !db.public_custom_data.is_empty() {
+                Writer::write_header(&mut out, version, Header::PublicCustomData as u8,
+                                     &db.public_custom_data.to_bytes())?;
+            }
+        }
+
+        for &(id, ref data) in db.other_headers.iter().filter(|x| 0 != x.0) {
+            Writer::write_header(&mut out, version, id, data)?;
+        }
+
+        let end = db.other_headers
+                    .iter()
+                    .find(|x| Header::End as u8 == x.0)
+                    .map(|x| &x.1[..])
+                    .unwrap_or(&END_OF_HEADER);
+        Writer::write_header(&mut out, version, Header::End as u8, end)?;
+
+        Ok(out)
+    }
+
+    /// Copies the document and the inner header binaries into the layout
+    /// `db.version` uses. This only changes anything when `version` was set
+    /// directly instead of through `set_version`: timestamps are written in
+    /// the version's encoding if its major version differs from the one the
+    /// document was read or converted for, and binaries are moved between
+    /// `<Meta>` and the inner header.
+    fn prepare(db : &Database) -> Result<(Element, Vec<Binary>), Error> {
+        let mut doc = db.xml().clone();
+        let mut binaries = db.binaries.clone();
+
+        if db.xml_version.major != db.version.major {
+            convert::convert_times(&mut doc, &db.version);
+        }
+
+        if 3 == db.version.major {
+            if !binaries.is_empty() {
+                convert::take_meta_binaries(&mut doc, &mut binaries)?;
+                convert::put_meta_binaries(&mut doc, &binaries,
+                                           Compression::GZip == db.compression)?;
+                binaries.clear();
+            }
+        } else {
+            convert::take_meta_binaries(&mut doc, &mut binaries)?;
+        }
+
+        Ok((doc, binaries))
+    }
+
+    /// Serializes the inner header KDBX 4 stores in front of the XML.
+    fn write_inner_header(db : &Database, seeds : &Seeds, binaries : &[Binary])
+                          -> Result<Vec<u8>, Error> {
+        fn field(w : &mut Vec<u8>, id : u8, data : &[u8]) -> Result<(), Error> {
+            w.write_u8(id)?;
+            w.write_u32::<LittleEndian>(data.len() as u32)?;
+            w.write_all(data)?;
+            Ok(())
+        }
+
+        let mut out = Vec::new();
+
+        let mut stream_id = [0u8; 4];
+        LittleEndian::write_u32(&mut stream_id, db.inner_stream_cipher.id());
+        field(&mut out, InnerHeader::InnerRandomStreamId as u8, &stream_id)?;
+
+        if let Some(ref key) = seeds.inner_stream_key {
+            field(&mut out, InnerHeader::InnerRandomStreamKey as u8, key)?;
+        }
+
+        for binary in binaries {
+            let mut data = Vec::with_capacity(binary.data.len() + 1);
+            data.push(binary.protected as u8);
+            data.extend_from_slice(&binary.data);
+            field(&mut out, InnerHeader::Binary as u8, &data)?;
+        }
+
+        for &(id, ref data) in &db.other_inner_headers {
+            field(&mut out, id, data)?;
+        }
+
+        field(&mut out, InnerHeader::End as u8, &[])?;
+        Ok(out)
+    }
+
+    /// Serializes the XML document with protected values encrypted. KDBX 3.1
+    /// also stores the hash of the header in the XML, while KDBX 4 protects
+    /// the header with an HMAC instead.
+    fn write_xml(db : &Database, seeds : &Seeds, mut doc : Element,
+                 header_hash : Option<&[u8]>) -> Result<Vec<u8>, Error> {
+        if let Some(meta) = doc.find_mut("Meta") {
+            match header_hash {
+                Some(hash) => {
+                    xml::ordered_child(meta, "HeaderHash", META_ORDER)
+                        .set_text(base64::encode(hash));
+                },
+                None => {
+                    while let Some(idx) = meta.children()
+                                              .position(|x| x.tag().name() == "HeaderHash") {
+                        meta.remove_child(idx);
+                    }
+                },
+            }
+        }
+
+        let mut stream = db.inner_stream(seeds)?;
+        stream::protect(&mut doc, &mut stream)?;
+
+        let mut bytes = Vec::new();
+        doc.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn compress(db : &Database, bytes : Vec<u8>) -> Result<Vec<u8>, Error> {
+        match db.compression {
+            Compression::None => Ok(bytes),
+            Compression::GZip => {
+                let mut encoder = GzEncoder::new(Vec::new(),
+                                                 flate2::Compression::Default);
+                encoder.write_all(&bytes)?;
+                Ok(encoder.finish()?)
+            },
+        }
+    }
+
+    /// Splits data into the hashed block stream read by `Reader::read_xml`,
+    /// ending with an empty block.
+    fn write_blocks(data : &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        let mut index = 0u32;
+
+        for block in data.chunks(BLOCK_SIZE) {
+            out.write_u32::<LittleEndian>(index)?;
+            out.write_all(&sha::sha256(block))?;
+            out.write_u32::<LittleEndian>(block.len() as u32)?;
+            out.write_all(block)?;
+            index += 1;
+        }
+
+        out.write_u32::<LittleEndian>(index)?;
+        out.write_all(&[0u8; 32])?;
+        out.write_u32::<LittleEndian>(0)?;
+        Ok(out)
+    }
+
+    /// Splits the encrypted payload into the HMAC block stream read by
+    /// `Reader::read_hmac_blocks`, ending with an empty block.
+    fn write_hmac_blocks(data : &[u8], hmac_key : &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        let mut index = 0u64;
+
+        for block in data.chunks(BLOCK_SIZE) {
+            out.write_all(&hmac::block_hmac(hmac_key, index, block)?)?;
+            out.write_i32::<LittleEndian>(block.len() as i32)?;
+            out.write_all(block)?;
+            index += 1;
+        }
+
+        out.write_all(&hmac::block_hmac(hmac_key, index, &[])?)?;
+        out.write_i32::<LittleEndian>(0)?;
+        Ok(out)
+    }
+
+    fn write_v3(&self, db : &Database, seeds : &Seeds, w : &mut dyn Write) -> Result<(), Error> {
+        if Kdf::Aes != db.kdf {
+            return Err(Error::new("kdbx 3.1 only supports the aes kdf"));
+        }
+
+        if seeds.stream_start_bytes.is_empty() {
+            return Err(Error::new("missing stream start bytes"));
+        }
+
+        let (doc, _) = Writer::prepare(db)?;
+        let header = Writer::write_headers(db, seeds)?;
+        let xml = Writer::write_xml(db, seeds, doc, Some(&sha::sha256(&header)))?;
+
+        let mut plaintext = seeds.stream_start_bytes.clone();
+        plaintext.extend(Writer::write_blocks(&Writer::compress(db, xml)?)?);
+
+        let master_key = seeds.master_key(&db.transformed_key(seeds, &self.key)?)?;
+        let ciphertext = db.outer_cipher.encrypt(&master_key,
+                                                 &seeds.encryption_iv,
+                                                 &plaintext)?;
+
+        w.write_all(&header)?;
+        w.write_all(&ciphertext)?;
+        Ok(())
+    }
+
+    fn write_v4(&self, db : &Database, seeds : &Seeds, w : &mut dyn Write) -> Result<(), Error> {
+        let header = Writer::write_headers(db, seeds)?;
+
+        let transformed_key = db.transformed_key(seeds, &self.key)?;
+        let hmac_key = seeds.hmac_key(&transformed_key)?;
+
+        let (doc, binaries) = Writer::prepare(db)?;
+        let mut payload = Writer::write_inner_header(db, seeds, &binaries)?;
+        payload.extend(Writer::write_xml(db, seeds, doc, None)?);
+
+        let ciphertext = db.outer_cipher.encrypt(&seeds.master_key(&transformed_key)?,
+                                                 &seeds.encryption_iv,
+                                                 &Writer::compress(db, payload)?)?;
+
+        w.write_all(&header)?;
+        w.write_all(&sha::sha256(&header))?;
+        w.write_all(&hmac::header_hmac(&hmac_key, &header)?)?;
+        w.write_all(&Writer::write_hmac_blocks(&ciphertext, &hmac_key)?)?;
+        Ok(())
+    }
+
+    pub fn write_to(&self, db : &Database, w : &mut dyn Write) -> Result<(), Error> {
+        match (db.version.major, db.outer_cipher) {
+            (3, OuterCipher::Aes128)    => (),
+            (3, OuterCipher::ChaCha20)  => {
+                return Err(Error::new("kdbx 3.1 only supports the aes cipher"));
+            },
+            (4, OuterCipher::Aes128)    => (),
+            (4, OuterCipher::ChaCha20)  => (),
+            _                           => return Err(Error::new("unsupported version")),
+        }
+
+        let seeds = if self.fixed_seeds {
+            db.seeds()
+        } else {
+            db.random_seeds()?
+        };
+
+        self.write_with_seeds(db, &seeds, w)
+    }
+
+    fn write_with_seeds(&self, db : &Database, seeds : &Seeds, w : &mut dyn Write)
+                        -> Result<(), Error> {
+        match db.version.major {
+            3 => self.write_v3(db, seeds, w),
+            _ => self.write_v4(db, seeds, w),
+        }
+    }
+
+    /// Saves a database to `path` without ever leaving a partially written
+    /// file behind. The database is written to a temporary file in the same
+    /// directory, which is synced and read back with the same key before it
+    /// is renamed over `path`. On Unix the new file is only readable by its
+    /// owner.
+    ///
+    /// If `backups` is not zero, the previous file is first copied to a
+    /// timestamped backup next to it. Once the new file is in place, the
+    /// oldest backups are removed so at most `backups` of them remain. Errors
+    /// while removing them are ignored, as the save has already succeeded.
+    pub fn save_to_path<P : AsRef<Path>>(&self, db : &Database, path : P,
+                                         backups : usize) -> Result<(), Error> {
+        save::replace(path.as_ref(), backups,
+                      |w| self.write_to(db, w),
+                      |r| match Reader::new(self.key.clone()).read_from(r) {
+                          Ok(_)   => Ok(()),
+                          Err(_)  => Err(Error::new("saved database failed to verify")),
+                      })
+    }
+}
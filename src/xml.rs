@@ -0,0 +1,159 @@
+use ::Uuid;
+
+use elementtree::Element;
+
+pub fn child_text<'a>(e : &'a Element, tag : &str) -> Option<&'a str> {
+    e.children().find(|x| x.tag().name() == tag).map(|x| x.text())
+}
+
+pub fn set_child_text<S : Into<String>>(e : &mut Element, tag : &str, text : S) {
+    if e.find(tag).is_none() {
+        e.append_new_child(tag);
+    }
+
+    e.find_mut(tag).unwrap().set_text(text);
+}
+
+/// Inserts `child` so that it ends up at position `idx` among the children
+/// of `e`.
+pub fn insert_child(e : &mut Element, idx : usize, child : Element) {
+    let mut tail = Vec::new();
+    while e.child_count() > idx {
+        tail.push(e.remove_child(idx).unwrap());
+    }
+
+    e.append_child(child);
+    for x in tail {
+        e.append_child(x);
+    }
+}
+
+/// Returns the `tag` child of `e`, creating it if it doesn't exist yet. New
+/// children are placed before the first child that comes after `tag` in
+/// `order`, which lists the element names in the order KeePass writes them.
+pub fn ordered_child<'a>(e : &'a mut Element, tag : &str, order : &[&str])
+                         -> &'a mut Element {
+    if e.find(tag).is_none() {
+        let idx = ordered_index(e, tag, order);
+        insert_child(e, idx, Element::new(tag));
+    }
+
+    e.children_mut().find(|x| x.tag().name() == tag).unwrap()
+}
+
+/// Returns the position for a new `tag` child of `e`: after any existing
+/// ones, and before the first child that comes after `tag` in `order`.
+pub fn ordered_index(e : &Element, tag : &str, order : &[&str]) -> usize {
+    let later = order.iter()
+                     .position(|x| *x == tag)
+                     .map(|x| &order[x + 1..])
+                     .unwrap_or(&[]);
+
+    e.children()
+     .position(|x| later.contains(&x.tag().name()))
+     .unwrap_or(e.child_count())
+}
+
+/// Adds an `<Entry>` or `<Group>` to a group. Entries go after the group's
+/// other entries and groups after its other groups, which is the layout
+/// KeePass writes.
+pub fn append_item(group : &mut Element, item : Element) {
+    let idx = match item.tag().name() {
+        "Entry" => group.children()
+                        .position(|x| x.tag().name() == "Group")
+                        .unwrap_or(group.child_count()),
+        _       => group.child_count(),
+    };
+
+    insert_child(group, idx, item);
+}
+
+pub fn parse_bool(s : &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "true"  => Some(true),
+        "false" => Some(false),
+        _       => None,
+    }
+}
+
+pub fn format_bool(b : bool) -> &'static str {
+    if b { "True" } else { "False" }
+}
+
+/// Decodes the `<UUID>` child of a group or entry.
+pub fn item_uuid(e : &Element) -> Option<Uuid> {
+    child_text(e, "UUID").and_then(|x| Uuid::from_base64(x).ok())
+}
+
+/// Removes the first `tag` element with the given UUID from `group` or any
+/// group nested inside it.
+pub fn take_item(group : &mut Element, tag : &str, uuid : &Uuid)
+                 -> Option<Element> {
+    let idx = group.children()
+                   .position(|x| x.tag().name() == tag
+                                 && item_uuid(x).as_ref() == Some(uuid));
+
+    if let Some(idx) = idx {
+        return group.remove_child(idx);
+    }
+
+    for child in group.find_all_mut("Group") {
+        if let Some(x) = take_item(child, tag, uuid) {
+            return Some(x);
+        }
+    }
+
+    None
+}
+
+/// Finds the `tag` element with the given UUID below `group`, returning the
+/// chain of groups leading to it followed by the element itself.
+pub fn find_item<'a>(group : &'a Element, tag : &str, uuid : &Uuid)
+                     -> Option<Vec<&'a Element>> {
+    if group.tag().name() == tag
+        && item_uuid(group).as_ref() == Some(uuid) {
+        return Some(vec![group]);
+    }
+
+    for child in group.children() {
+        let name = child.tag().name();
+
+        if name == tag && item_uuid(child).as_ref() == Some(uuid) {
+            return Some(vec![group, child]);
+        }
+
+        if name == "Group" {
+            if let Some(mut path) = find_item(child, tag, uuid) {
+                path.insert(0, group);
+                return Some(path);
+            }
+        }
+    }
+
+    None
+}
+
+/// Like `find_item`, but returns only the matching element, mutably.
+pub fn find_item_mut<'a>(group : &'a mut Element, tag : &str, uuid : &Uuid)
+                         -> Option<&'a mut Element> {
+    if group.tag().name() == tag
+        && item_uuid(group).as_ref() == Some(uuid) {
+        return Some(group);
+    }
+
+    for child in group.children_mut() {
+        let name = child.tag().name().to_owned();
+
+        if name == tag && item_uuid(child).as_ref() == Some(uuid) {
+            return Some(child);
+        }
+
+        if name == "Group" {
+            if let Some(x) = find_item_mut(child, tag, uuid) {
+                return Some(x);
+            }
+        }
+    }
+
+    None
+}
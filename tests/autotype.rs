@@ -0,0 +1,129 @@
+extern crate kdbx;
+extern crate openssl;
+extern crate elementtree;
+
+mod common;
+
+use elementtree::Element;
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+const AUTO_TYPE : &'static str = r#"<AutoType>
+    <Enabled>False</Enabled>
+    <DataTransferObfuscation>1</DataTransferObfuscation>
+    <DefaultSequence>{PASSWORD}{ENTER}</DefaultSequence>
+    <Association>
+        <Window>Login - *</Window>
+        <KeystrokeSequence />
+    </Association>
+</AutoType>"#;
+
+#[test]
+fn auto_type_from_xml() {
+    let elem = Element::from_reader(AUTO_TYPE.as_bytes()).unwrap();
+    let auto_type = AutoType::from_xml(&elem).unwrap();
+
+    assert_eq!(auto_type.enabled, false);
+    assert_eq!(auto_type.data_transfer_obfuscation, 1);
+    assert_eq!(auto_type.default_sequence, "{PASSWORD}{ENTER}");
+    assert_eq!(auto_type.associations,
+               vec![AutoTypeAssociation {
+                   window: "Login - *".to_owned(),
+                   sequence: "".to_owned(),
+               }]);
+}
+
+#[test]
+fn auto_type_write_xml_round_trip() {
+    let elem = Element::from_reader(AUTO_TYPE.as_bytes()).unwrap();
+    let mut auto_type = AutoType::from_xml(&elem).unwrap();
+    auto_type.associations.pop();
+
+    let mut out = elem.clone();
+    auto_type.write_xml(&mut out);
+    assert_eq!(AutoType::from_xml(&out).unwrap(), auto_type);
+}
+
+#[test]
+fn association_matches_wildcards() {
+    let assoc = AutoTypeAssociation {
+        window: "*Test Form - KeePass*".to_owned(),
+        sequence: "".to_owned(),
+    };
+
+    assert!(assoc.matches("Test Form - KeePass"));
+    assert!(assoc.matches("test form - keepass - Mozilla Firefox"));
+    assert!(!assoc.matches("Test Form"));
+}
+
+#[test]
+fn association_matches_many_wildcards_quickly() {
+    let assoc = AutoTypeAssociation {
+        window: "*a*a*a*a*a*a*a*a*a*b".to_owned(),
+        sequence: "".to_owned(),
+    };
+
+    let title = "a".repeat(1000);
+    assert!(!assoc.matches(&title));
+    assert!(assoc.matches(&format!("{}b", title)));
+
+    let assoc = AutoTypeAssociation {
+        window: "a*b*c".to_owned(),
+        sequence: "".to_owned(),
+    };
+    assert!(assoc.matches("abc"));
+    assert!(assoc.matches("a-b-b-c"));
+    assert!(!assoc.matches("a-b-c-"));
+    assert!(!assoc.matches("ab"));
+}
+
+#[test]
+fn database_auto_type_sequence() {
+    let db = open(DB_AES256_PLAIN);
+    let entries = db.entries();
+    let first = entries[0].uuid().unwrap();
+    let second = entries[1].uuid().unwrap();
+
+    assert_eq!(db.auto_type_enabled(&first).unwrap(), true);
+    assert_eq!(db.auto_type_sequence(&first, "Target Window").unwrap(),
+               Some("{USERNAME}{TAB}{PASSWORD}{TAB}{ENTER}".to_owned()));
+    assert_eq!(db.auto_type_sequence(&first, "Other Window").unwrap(), None);
+
+    assert_eq!(db.auto_type_sequence(&second, "Test Form - KeePass - Firefox").unwrap(),
+               Some("{USERNAME}{TAB}{PASSWORD}{ENTER}".to_owned()));
+}
+
+#[test]
+fn database_auto_type_sequence_of_group() {
+    let db = open(DB_AES256_PLAIN);
+    let root = db.root_group().unwrap().uuid().unwrap();
+
+    assert!(db.auto_type_enabled(&root).unwrap());
+    assert!(db.auto_type_sequence(&root, "Target Window").is_err());
+}
+
+#[test]
+fn database_auto_type_matches() {
+    let db = open(DB_AES256_PLAIN);
+    let matches = db.auto_type_matches("Target Window").unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.title(), Some("Sample Entry"));
+}
+
+#[test]
+fn database_auto_type_title_fallback() {
+    let db = open(DB_AES256_PLAIN);
+    let entries = db.entries();
+    let second = entries[1].uuid().unwrap();
+
+    // "Sample Entry #2" has no association for this window, but its title
+    // appears in the window title.
+    assert_eq!(db.auto_type_sequence(&second, "Login: sample entry #2 - Firefox").unwrap(),
+               Some("{USERNAME}{TAB}{PASSWORD}{ENTER}".to_owned()));
+    assert_eq!(db.auto_type_sequence(&second, "Sample Entry").unwrap(), None);
+
+    let matches = db.auto_type_matches("Sample Entry - Firefox").unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.title(), Some("Sample Entry"));
+}
@@ -0,0 +1,96 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use std::io::{Cursor, Write};
+
+use openssl::sha::sha256;
+use openssl::symm::{self, Cipher, Crypter, Mode};
+
+use kdbx::*;
+use kdbx::keys::{PasswordKey, CompositeKey};
+
+pub const DB_AES256_PLAIN : &[u8] =
+    include_bytes!("../samples/AES256.Uncompressed.kdbx");
+
+pub const DB_AES256_GZIP : &[u8] =
+    include_bytes!("../samples/AES256.GZIP.kdbx");
+
+/// The master key of every sample.
+pub const PASSWORD : &str = "hello world";
+
+pub fn key(password : &str) -> CompositeKey {
+    let pw = PasswordKey::from(password);
+    let mut cm = CompositeKey::new().unwrap();
+    cm.push(&pw);
+    cm
+}
+
+pub fn open(bytes : &[u8]) -> Database {
+    Reader::new(key(PASSWORD)).read_from(&mut Cursor::new(bytes)).unwrap()
+}
+
+/// Writes the database with fresh seeds, like a real save.
+pub fn save(db : &Database) -> Vec<u8> {
+    let mut out = Vec::new();
+    db.write_to(&mut out).unwrap();
+    out
+}
+
+/// Writes the database with the seeds it already has, so the output can be
+/// compared byte for byte.
+pub fn save_fixed(db : &Database) -> Vec<u8> {
+    let mut out = Vec::new();
+    Writer::new(key(PASSWORD)).with_fixed_seeds().write_to(db, &mut out).unwrap();
+    out
+}
+
+/// Encrypts `xml` as a KDBX 3.1 file by hand, without going through
+/// `Writer`, so the tests can read documents `validate_xml` would reject.
+pub fn encrypt_v3(xml : &str) -> Vec<u8> {
+    let transform_seed = [1u8; 32];
+    let master_seed = [2u8; 32];
+    let iv = [3u8; 16];
+    let start = [4u8; 32];
+
+    let composite = sha256(&sha256(PASSWORD.as_bytes()));
+    let mut crypter = Crypter::new(Cipher::aes_256_ecb(), Mode::Encrypt,
+                                   &transform_seed, None).unwrap();
+    crypter.pad(false);
+    let mut transformed = vec![0u8; 48];
+    crypter.update(&composite, &mut transformed).unwrap();
+
+    let mut seeded = master_seed.to_vec();
+    seeded.extend_from_slice(&sha256(&transformed[..32]));
+    let master_key = sha256(&seeded);
+
+    let mut out = vec![0x03, 0xD9, 0xA2, 0x9A, 0x67, 0xFB, 0x4B, 0xB5, 1, 0, 3, 0];
+    let headers : Vec<(u8, Vec<u8>)> = vec![
+        (2, vec![0x31, 0xc1, 0xf2, 0xe6, 0xbf, 0x71, 0x43, 0x50,
+                 0xbe, 0x58, 0x05, 0x21, 0x6a, 0xfc, 0x5a, 0xff]),
+        (3, vec![0, 0, 0, 0]),
+        (4, master_seed.to_vec()),
+        (5, transform_seed.to_vec()),
+        (6, vec![1, 0, 0, 0, 0, 0, 0, 0]),
+        (7, iv.to_vec()),
+        (8, vec![5u8; 32]),
+        (9, start.to_vec()),
+        (10, vec![0, 0, 0, 0]),
+        (0, b"\r\n\r\n".to_vec()),
+    ];
+    for (id, data) in headers {
+        out.push(id);
+        out.write_all(&(data.len() as u16).to_le_bytes()).unwrap();
+        out.write_all(&data).unwrap();
+    }
+
+    let mut plaintext = start.to_vec();
+    plaintext.write_all(&0u32.to_le_bytes()).unwrap();
+    plaintext.write_all(&sha256(xml.as_bytes())).unwrap();
+    plaintext.write_all(&(xml.len() as u32).to_le_bytes()).unwrap();
+    plaintext.write_all(xml.as_bytes()).unwrap();
+    plaintext.write_all(&1u32.to_le_bytes()).unwrap();
+    plaintext.write_all(&[0u8; 36]).unwrap();
+
+    out.extend(symm::encrypt(Cipher::aes_256_cbc(), &master_key, Some(&iv), &plaintext).unwrap());
+    out
+}
@@ -0,0 +1,281 @@
+extern crate base64;
+extern crate elementtree;
+extern crate flate2;
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::io::Write;
+
+use elementtree::Element;
+use flate2::write::GzEncoder;
+
+use kdbx::*;
+use common::{DB_AES256_GZIP, open, save};
+
+const DB_KDBX41 : &'static [u8] =
+    include_bytes!("samples/Argon2id.ChaCha20.KDBX41.kdbx");
+
+fn v(major : u16, minor : u16) -> Version {
+    Version { major: major, minor: minor }
+}
+
+fn creation_time(db : &Database) -> String {
+    db.xml()
+      .find("Root").unwrap()
+      .find("Group").unwrap()
+      .find("Times").unwrap()
+      .find("CreationTime").unwrap()
+      .text()
+      .to_owned()
+}
+
+fn element(tag : &str, text : &str) -> Element {
+    let mut e = Element::new(tag);
+    e.set_text(text);
+    e
+}
+
+/// Adds two binaries to `<Meta>` the way KDBX 3.1 stores them, one of them
+/// compressed, and attaches them to the second entry.
+fn add_binaries(db : &mut Database) {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::Default);
+    encoder.write_all(b"compressed").unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    db.edit_xml(|doc| {
+        let meta = doc.find_mut("Meta").unwrap();
+        let idx = meta.children().position(|x| x.tag().name() == "Binaries").unwrap();
+        let pool = meta.get_child_mut(idx).unwrap();
+
+        let mut first = element("Binary", &base64::encode(&compressed));
+        first.set_attr("ID", "0");
+        first.set_attr("Compressed", "True");
+        pool.append_child(first);
+
+        let mut second = element("Binary", &base64::encode(b"protected"));
+        second.set_attr("ID", "3");
+        second.set_attr("Protected", "True");
+        pool.append_child(second);
+
+        let entry = doc.find_mut("Root").unwrap()
+                       .find_mut("Group").unwrap()
+                       .find_all_mut("Entry").nth(1).unwrap();
+        for &(name, id) in &[("a.txt", "3"), ("b.txt", "0")] {
+            let mut attachment = Element::new("Binary");
+            let mut value = Element::new("Value");
+            value.set_attr("Ref", id);
+            attachment.append_child(element("Key", name));
+            attachment.append_child(value);
+            entry.append_child(attachment);
+        }
+    }).unwrap();
+}
+
+fn refs(db : &Database) -> Vec<String> {
+    db.xml()
+      .find("Root").unwrap()
+      .find("Group").unwrap()
+      .find_all("Entry").nth(1).unwrap()
+      .find_all("Binary")
+      .map(|x| x.find("Value").unwrap().get_attr("Ref").unwrap().to_owned())
+      .collect()
+}
+
+#[test]
+fn upgrade() {
+    let mut db = open(DB_AES256_GZIP);
+    assert!(creation_time(&db).contains('-'));
+
+    db.set_version(v(4, 0)).unwrap();
+    assert_eq!(db.version, v(4, 0));
+    assert_eq!(db.inner_stream_cipher, InnerStreamCipher::ChaCha20);
+    assert_eq!(db.inner_stream_key.as_ref().unwrap().len(), 64);
+    assert!(db.stream_start_bytes.is_empty());
+    assert!(!creation_time(&db).contains('-'));
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.version, v(4, 0));
+    assert_eq!(creation_time(&copy), creation_time(&db));
+
+    let entry = &copy.entries()[1];
+    assert_eq!(entry.field("UserName"), Some("Michael321"));
+    assert_eq!(entry.field("Password"), Some("12345"));
+}
+
+#[test]
+fn upgrade_binaries() {
+    let mut db = open(DB_AES256_GZIP);
+    add_binaries(&mut db);
+
+    db.set_version(v(4, 1)).unwrap();
+    assert!(db.xml().find("Meta").unwrap().find("Binaries").is_none());
+    assert_eq!(db.binaries, vec![
+        Binary { protected: false, data: b"compressed".to_vec() },
+        Binary { protected: true, data: b"protected".to_vec() },
+    ]);
+    assert_eq!(refs(&db), vec!["1", "0"]);
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.binaries, db.binaries);
+    assert_eq!(refs(&copy), vec!["1", "0"]);
+}
+
+#[test]
+fn downgrade_binaries() {
+    let mut db = open(DB_AES256_GZIP);
+    add_binaries(&mut db);
+    db.set_version(v(4, 0)).unwrap();
+
+    db.set_version(v(3, 1)).unwrap();
+    assert!(db.binaries.is_empty());
+    assert_eq!(db.inner_stream_cipher, InnerStreamCipher::Salsa20);
+    assert_eq!(db.stream_start_bytes.len(), 32);
+    assert!(creation_time(&db).contains('-'));
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.version, v(3, 1));
+    assert_eq!(refs(&copy), vec!["1", "0"]);
+
+    let mut copy = copy;
+    copy.set_version(v(4, 0)).unwrap();
+    assert_eq!(copy.binaries, vec![
+        Binary { protected: false, data: b"compressed".to_vec() },
+        Binary { protected: true, data: b"protected".to_vec() },
+    ]);
+}
+
+#[test]
+fn downgrade_meta_binaries() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(v(4, 0)).unwrap();
+    db.binaries.push(Binary { protected: true, data: b"protected".to_vec() });
+    db.binaries.push(Binary { protected: false, data: b"plain".to_vec() });
+
+    db.set_version(v(3, 1)).unwrap();
+    let pool = db.xml().find("Meta").unwrap().find("Binaries").unwrap();
+    let attrs : Vec<_> = pool.find_all("Binary")
+                             .map(|x| (x.get_attr("Protected"), x.get_attr("Compressed")))
+                             .collect();
+    assert_eq!(attrs, vec![(Some("True"), None), (None, Some("True"))]);
+
+    let mut copy = open(&save(&db));
+    copy.set_version(v(4, 0)).unwrap();
+    assert_eq!(copy.binaries, vec![
+        Binary { protected: true, data: b"protected".to_vec() },
+        Binary { protected: false, data: b"plain".to_vec() },
+    ]);
+}
+
+fn count(e : &Element, tag : &str) -> usize {
+    let own = if e.tag().name() == tag { 1 } else { 0 };
+    own + e.children().map(|x| count(x, tag)).sum::<usize>()
+}
+
+fn group_tags(db : &Database) -> usize {
+    fn walk(e : &Element) -> usize {
+        e.find_all("Group").map(|x| x.find_all("Tags").count() + walk(x)).sum()
+    }
+    walk(db.xml().find("Root").unwrap())
+}
+
+#[test]
+fn downgrade_kdbx41_elements() {
+    let mut db = open(DB_KDBX41);
+    assert!(count(db.xml(), "QualityCheck") > 0);
+    assert!(count(db.xml(), "PreviousParentGroup") > 0);
+    assert_eq!(group_tags(&db), 1);
+
+    db.set_version(v(4, 0)).unwrap();
+    assert_eq!(count(db.xml(), "QualityCheck"), 0);
+    assert_eq!(count(db.xml(), "PreviousParentGroup"), 0);
+    assert_eq!(group_tags(&db), 0);
+    assert_eq!(db.entries()[0].tags(), vec!["sample", "kdbx4"]);
+
+    let data = db.entries()[0].custom_data().unwrap();
+    assert_eq!(data.get("Plugin/Entry"), Some("1"));
+    assert!(data.get_item("Plugin/Entry").unwrap().last_modification_time.is_none());
+
+    db.kdf = Kdf::Aes;
+    db.outer_cipher = OuterCipher::Aes128;
+    db.set_version(v(3, 1)).unwrap();
+    assert!(db.entries()[0].custom_data().unwrap().is_empty());
+    assert!(db.meta().unwrap().custom_data().unwrap().get("Plugin/Database").is_some());
+    assert_eq!(db.entries()[0].tags(), vec!["sample", "kdbx4"]);
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.version, v(3, 1));
+    assert_eq!(count(copy.xml(), "CustomData"), 1);
+    assert_eq!(copy.entries()[0].field("Password"), Some("correct horse"));
+}
+
+#[test]
+fn change_settings() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(v(4, 0)).unwrap();
+    db.outer_cipher = OuterCipher::ChaCha20;
+    db.compression = Compression::None;
+    db.inner_stream_cipher = InnerStreamCipher::Salsa20;
+    db.kdf = Kdf::Argon2 {
+        variant: Argon2Variant::Argon2id,
+        memory: 1024 * 1024,
+        parallelism: 2,
+        version: 0x13,
+    };
+    db.transform_rounds = 2;
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.outer_cipher, OuterCipher::ChaCha20);
+    assert_eq!(copy.compression, Compression::None);
+    assert_eq!(copy.inner_stream_cipher, InnerStreamCipher::Salsa20);
+    assert_eq!(copy.kdf, db.kdf);
+    assert_eq!(copy.transform_rounds, 2);
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+}
+
+#[test]
+fn aes_rounds() {
+    let mut db = open(DB_AES256_GZIP);
+    db.transform_rounds = 1000;
+
+    let copy = open(&save(&db));
+    assert_eq!(copy.transform_rounds, 1000);
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+}
+
+#[test]
+fn downgrade_requires_aes() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(v(4, 0)).unwrap();
+
+    db.kdf = Kdf::Argon2 {
+        variant: Argon2Variant::Argon2d,
+        memory: 1024 * 1024,
+        parallelism: 2,
+        version: 0x13,
+    };
+    assert!(db.set_version(v(3, 1)).is_err());
+
+    db.kdf = Kdf::Aes;
+    db.outer_cipher = OuterCipher::ChaCha20;
+    assert!(db.set_version(v(3, 1)).is_err());
+    assert_eq!(db.version, v(4, 0));
+}
+
+#[test]
+fn unsupported_version() {
+    let mut db = open(DB_AES256_GZIP);
+    assert!(db.set_version(v(5, 0)).is_err());
+    assert!(db.set_version(v(3, 0)).is_err());
+    assert_eq!(db.version, v(3, 1));
+}
+
+#[test]
+fn v3_rejects_chacha20() {
+    let mut db = open(DB_AES256_GZIP);
+    db.outer_cipher = OuterCipher::ChaCha20;
+
+    let mut out = Vec::new();
+    assert!(db.write_to(&mut out).is_err());
+}
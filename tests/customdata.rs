@@ -0,0 +1,113 @@
+extern crate kdbx;
+extern crate openssl;
+extern crate elementtree;
+
+mod common;
+
+use elementtree::Element;
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+const CUSTOM_DATA : &'static str = r#"<CustomData>
+    <Item>
+        <Key>KPXC_DECRYPTION_TIME_PREFERENCE</Key>
+        <Value>100</Value>
+        <LastModificationTime>ZHsF0Q4AAAA=</LastModificationTime>
+    </Item>
+    <Item>
+        <Key>KeeAgent.Settings</Key>
+        <Value>&lt;EntrySettings /&gt;</Value>
+    </Item>
+</CustomData>"#;
+
+#[test]
+fn custom_data_from_xml() {
+    let elem = Element::from_reader(CUSTOM_DATA.as_bytes()).unwrap();
+    let data = CustomData::from_xml(&elem).unwrap();
+
+    assert_eq!(data.len(), 2);
+    assert_eq!(data.get("KPXC_DECRYPTION_TIME_PREFERENCE"), Some("100"));
+    assert_eq!(data.get("KeeAgent.Settings"), Some("<EntrySettings />"));
+    assert!(data.items()[0].last_modification_time.is_some());
+    assert!(data.items()[1].last_modification_time.is_none());
+}
+
+#[test]
+fn custom_data_set_and_remove() {
+    let mut data = CustomData::new();
+    data.set("a", "1");
+    data.set("b", "2");
+    data.set("a", "3");
+
+    assert_eq!(data.get("a"), Some("3"));
+    assert_eq!(data.items()[0].key, "a");
+    assert_eq!(data.remove("a"), Some("3".to_owned()));
+    assert_eq!(data.remove("a"), None);
+    assert_eq!(data.len(), 1);
+}
+
+#[test]
+fn custom_data_write_xml_by_version() {
+    let elem = Element::from_reader(CUSTOM_DATA.as_bytes()).unwrap();
+    let data = CustomData::from_xml(&elem).unwrap();
+
+    let mut v40 = Element::new("CustomData");
+    data.write_xml(&mut v40, &Version { major: 4, minor: 0 });
+    assert!(v40.find("Item").unwrap().find("LastModificationTime").is_none());
+
+    let mut v41 = Element::new("CustomData");
+    data.write_xml(&mut v41, &Version { major: 4, minor: 1 });
+    assert_eq!(CustomData::from_xml(&v41).unwrap(), data);
+}
+
+#[test]
+fn database_meta_custom_data() {
+    let mut db = open(DB_AES256_PLAIN);
+    assert!(db.meta().unwrap().custom_data().unwrap().is_empty());
+
+    let mut data = CustomData::new();
+    data.set("KeePassXC-Browser Settings", "{}");
+    db.meta_mut().unwrap().set_custom_data(&data);
+
+    let read = db.meta().unwrap().custom_data().unwrap();
+    assert_eq!(read.get("KeePassXC-Browser Settings"), Some("{}"));
+}
+
+#[test]
+fn database_entry_and_group_custom_data() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.set_version(Version { major: 4, minor: 0 }).unwrap();
+    let entry = db.entries()[0].uuid().unwrap();
+    let group = db.root_group().unwrap().uuid().unwrap();
+
+    let mut data = CustomData::new();
+    data.set("ourapp.owner", "infra");
+
+    db.entry_mut(&entry).unwrap().set_custom_data(&data).unwrap();
+    db.group_mut(&group).unwrap().set_custom_data(&data).unwrap();
+
+    assert_eq!(db.entry(&entry).unwrap().custom_data().unwrap().get("ourapp.owner"),
+               Some("infra"));
+    assert_eq!(db.group(&group).unwrap().custom_data().unwrap().get("ourapp.owner"),
+               Some("infra"));
+
+    // The group's entries and subgroups are unaffected by the insertion.
+    assert_eq!(db.root_group().unwrap().entries().len(), 2);
+    assert_eq!(db.root_group().unwrap().groups().len(), 6);
+}
+
+#[test]
+fn database_entry_and_group_custom_data_v3() {
+    let mut db = open(DB_AES256_PLAIN);
+    let entry = db.entries()[0].uuid().unwrap();
+    let group = db.root_group().unwrap().uuid().unwrap();
+
+    let mut data = CustomData::new();
+    data.set("ourapp.owner", "infra");
+
+    assert!(db.entry_mut(&entry).unwrap().set_custom_data(&data).is_err());
+    assert!(db.group_mut(&group).unwrap().set_custom_data(&data).is_err());
+
+    assert!(db.entry(&entry).unwrap().custom_data().unwrap().is_empty());
+    assert!(db.group(&group).unwrap().custom_data().unwrap().is_empty());
+}
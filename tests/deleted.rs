@@ -0,0 +1,77 @@
+extern crate kdbx;
+extern crate openssl;
+extern crate chrono;
+
+mod common;
+
+use chrono::Duration;
+use common::{DB_AES256_PLAIN, open};
+
+#[test]
+fn deleted_objects_empty() {
+    let db = open(DB_AES256_PLAIN);
+    assert_eq!(db.deleted_objects().unwrap(), vec![]);
+}
+
+#[test]
+fn remove_entry_records_tombstone() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    db.remove_entry(&uuid).unwrap();
+
+    assert_eq!(db.entries().len(), 1);
+    let deleted = db.deleted_objects().unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].uuid, uuid);
+
+    assert!(db.remove_entry(&uuid).is_err());
+}
+
+#[test]
+fn remove_group_records_tombstone() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.root_group().unwrap().groups()[0].uuid().unwrap();
+
+    db.remove_group(&uuid).unwrap();
+
+    assert_eq!(db.root_group().unwrap().groups().len(), 5);
+    let deleted = db.deleted_objects().unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].uuid, uuid);
+}
+
+#[test]
+fn remove_root_group_fails() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.root_group().unwrap().uuid().unwrap();
+
+    assert!(db.remove_group(&uuid).is_err());
+    assert_eq!(db.deleted_objects().unwrap(), vec![]);
+}
+
+#[test]
+fn prune_deleted_objects() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+    db.remove_entry(&uuid).unwrap();
+
+    assert_eq!(db.prune_deleted_objects(Duration::days(1)).unwrap(), 0);
+    assert_eq!(db.prune_deleted_objects(Duration::days(-1)).unwrap(), 1);
+    assert_eq!(db.deleted_objects().unwrap(), vec![]);
+}
+
+#[test]
+fn prune_without_deleted_objects() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.edit_xml(|doc| {
+        let root = doc.find_mut("Root").unwrap();
+        while let Some(idx) = root.children()
+                                  .position(|x| x.tag().name() == "DeletedObjects") {
+            root.remove_child(idx);
+        }
+    }).unwrap();
+
+    assert_eq!(db.prune_deleted_objects(Duration::days(1)).unwrap(), 0);
+    assert!(db.xml().find("Root").unwrap().find("DeletedObjects").is_none());
+}
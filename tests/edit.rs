@@ -0,0 +1,244 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, encrypt_v3, open};
+
+fn root(db : &Database) -> Uuid {
+    db.root_group().unwrap().uuid().unwrap()
+}
+
+#[test]
+fn create_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    let parent = root(&db);
+
+    let uuid = db.create_group(&parent, "Infra").unwrap();
+    let other = db.create_group(&uuid, "Prod").unwrap();
+    assert!(!uuid.is_nil());
+    assert!(uuid != other);
+
+    let group = db.group_by_path("NewDatabase/Infra/Prod", &PathSyntax::new()).unwrap();
+    assert_eq!(group.uuid(), Some(other));
+    assert_eq!(group.enable_auto_type(), None);
+
+    let times = group.times().unwrap();
+    assert_eq!(times.creation_time, times.last_modification_time);
+    assert_eq!(times.usage_count, 0);
+
+    assert!(db.create_group(&Uuid::nil(), "Missing").is_err());
+    Database::validate_xml(db.xml()).unwrap();
+}
+
+#[test]
+fn create_entry() {
+    let mut db = open(DB_AES256_PLAIN);
+    let parent = root(&db);
+
+    let uuid = db.create_entry(&parent).unwrap();
+    let entry = db.entry(&uuid).unwrap();
+
+    assert_eq!(entry.field_names(), vec!["Title", "UserName", "Password", "URL", "Notes"]);
+    assert_eq!(entry.title(), Some(""));
+    assert!(entry.is_protected("Password"));
+    assert!(!entry.is_protected("Title"));
+    assert!(entry.history().is_empty());
+
+    let entries = db.root_group().unwrap().entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[2].uuid(), Some(uuid));
+    Database::validate_xml(db.xml()).unwrap();
+}
+
+#[test]
+fn update_entry_keeps_history() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+    let before = db.entry(&uuid).unwrap().times().unwrap();
+
+    db.update_entry(&uuid, |e| {
+        e.set_field("Password", "correct horse");
+        e.set_field("Environment", "prod");
+        assert!(e.remove_field("URL"));
+        assert!(!e.remove_field("Missing"));
+    }).unwrap();
+
+    let entry = db.entry(&uuid).unwrap();
+    assert_eq!(entry.field("Password"), Some("correct horse"));
+    assert_eq!(entry.field("Environment"), Some("prod"));
+    assert_eq!(entry.field("URL"), None);
+    assert!(entry.is_protected("Password"));
+
+    let after = entry.times().unwrap();
+    assert!(after.last_modification_time > before.last_modification_time);
+    assert_eq!(after.creation_time, before.creation_time);
+    assert_eq!(after.usage_count, before.usage_count + 1);
+
+    let history = entry.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].field("Password"), Some("Password"));
+    assert!(history[0].history().is_empty());
+
+    Database::validate_xml(db.xml()).unwrap();
+}
+
+#[test]
+fn update_entry_trims_history() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.edit_xml(|doc| {
+        doc.find_mut("Meta").unwrap()
+           .find_mut("HistoryMaxItems").unwrap()
+           .set_text("2");
+    }).unwrap();
+
+    let uuid = db.entries()[0].uuid().unwrap();
+    for password in &["one", "two", "three"] {
+        db.update_entry(&uuid, |e| e.set_field("Password", password)).unwrap();
+    }
+
+    let entry = db.entry(&uuid).unwrap();
+    let history : Vec<_> = entry.history()
+                                .iter()
+                                .map(|x| x.field("Password").unwrap())
+                                .collect();
+    assert_eq!(history, vec!["one", "two"]);
+}
+
+#[test]
+fn update_entry_without_changes() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+    let before = db.entry(&uuid).unwrap().times().unwrap();
+
+    db.update_entry(&uuid, |e| e.set_field("Password", "Password")).unwrap();
+
+    let entry = db.entry(&uuid).unwrap();
+    assert!(entry.history().is_empty());
+    assert_eq!(entry.times().unwrap(), before);
+}
+
+#[test]
+fn update_entry_trims_history_by_size() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    db.update_entry(&uuid, |e| e.set_field("Password", "one")).unwrap();
+    let size = {
+        let mut out = Vec::new();
+        db.xml().find("Root").unwrap()
+          .find("Group").unwrap()
+          .find("Entry").unwrap()
+          .find("History").unwrap()
+          .find("Entry").unwrap()
+          .to_writer(&mut out).unwrap();
+        out.len()
+    };
+
+    db.edit_xml(|doc| {
+        doc.find_mut("Meta").unwrap()
+           .find_mut("HistoryMaxSize").unwrap()
+           .set_text((size * 3 / 2).to_string());
+    }).unwrap();
+
+    db.update_entry(&uuid, |e| e.set_field("Password", "two")).unwrap();
+
+    let history : Vec<_> = db.entry(&uuid).unwrap()
+                             .history()
+                             .iter()
+                             .map(|x| x.field("Password").unwrap())
+                             .collect();
+    assert_eq!(history, vec!["one"]);
+}
+
+#[test]
+fn update_entry_restores_on_error() {
+    let xml = r#"<KeePassFile><Meta /><Root><Group>
+        <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID>
+        <Entry>
+            <UUID>AAAAAAAAAAAAAAAAAAAAAQ==</UUID>
+            <Times><UsageCount>lots</UsageCount></Times>
+            <String><Key>Title</Key><Value>before</Value></String>
+        </Entry>
+    </Group></Root></KeePassFile>"#;
+
+    let mut db = open(&encrypt_v3(xml));
+
+    let uuid = db.entries()[0].uuid().unwrap();
+    assert!(db.update_entry(&uuid, |e| e.set_field("Title", "after")).is_err());
+
+    let entry = db.entry(&uuid).unwrap();
+    assert_eq!(entry.title(), Some("before"));
+    assert!(entry.history().is_empty());
+}
+
+#[test]
+fn update_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    let parent = root(&db);
+    let uuid = db.create_group(&parent, "Infra").unwrap();
+
+    db.update_group(&uuid, |g| {
+        g.set_name("Infrastructure");
+        g.set_notes("Servers");
+    }).unwrap();
+
+    let group = db.group(&uuid).unwrap();
+    assert_eq!(group.name(), Some("Infrastructure"));
+    assert_eq!(group.notes(), Some("Servers"));
+    assert_eq!(group.times().unwrap().usage_count, 1);
+}
+
+#[test]
+fn update_group_without_changes() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = root(&db);
+    let before = db.group(&uuid).unwrap().times().unwrap();
+
+    db.update_group(&uuid, |_| ()).unwrap();
+
+    assert_eq!(db.group(&uuid).unwrap().times().unwrap(), before);
+}
+
+#[test]
+fn update_group_restores_on_error() {
+    let xml = r#"<KeePassFile><Meta /><Root><Group>
+        <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID>
+        <Name>before</Name>
+        <Times><UsageCount>lots</UsageCount></Times>
+    </Group></Root></KeePassFile>"#;
+
+    let mut db = open(&encrypt_v3(xml));
+
+    let uuid = root(&db);
+    assert!(db.update_group(&uuid, |g| g.set_name("after")).is_err());
+    assert_eq!(db.group(&uuid).unwrap().name(), Some("before"));
+}
+
+#[test]
+fn touch_entry() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[1].uuid().unwrap();
+    let before = db.entry(&uuid).unwrap().times().unwrap();
+
+    db.touch_entry(&uuid).unwrap();
+
+    let after = db.entry(&uuid).unwrap().times().unwrap();
+    assert_eq!(after.usage_count, before.usage_count + 1);
+    assert_eq!(after.last_modification_time, before.last_modification_time);
+    assert!(after.last_access_time > before.last_access_time);
+}
+
+#[test]
+fn create_then_delete_entry() {
+    let mut db = open(DB_AES256_PLAIN);
+    let parent = root(&db);
+    let uuid = db.create_entry(&parent).unwrap();
+
+    db.delete_entry(&uuid).unwrap();
+    db.delete_entry(&uuid).unwrap();
+
+    assert!(db.entry(&uuid).is_none());
+    assert_eq!(db.deleted_objects().unwrap()[0].uuid, uuid);
+}
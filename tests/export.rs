@@ -0,0 +1,117 @@
+extern crate elementtree;
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::io::Cursor;
+
+use elementtree::Element;
+
+use kdbx::*;
+use common::{DB_AES256_GZIP, open, save};
+
+fn export(db : &Database) -> String {
+    let mut out = Vec::new();
+    db.export_xml(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn v4() -> Version {
+    Version { major: 4, minor: 0 }
+}
+
+#[test]
+fn export_protected() {
+    let db = open(DB_AES256_GZIP);
+    let xml = export(&db);
+
+    assert!(xml.contains("ProtectInMemory=\"True\""));
+    assert!(!xml.contains("Protected="));
+    assert!(!xml.contains("HeaderHash"));
+    assert!(xml.contains(">12345<"));
+
+    let doc = Element::from_reader(&mut Cursor::new(xml)).unwrap();
+    assert!(Database::validate_xml(&doc).is_ok());
+}
+
+#[test]
+fn round_trip() {
+    let db = open(DB_AES256_GZIP);
+    let xml = export(&db);
+
+    let mut other = open(DB_AES256_GZIP);
+    other.edit_xml(|doc| {
+        let root = doc.find_mut("Root").unwrap().find_mut("Group").unwrap();
+        let idx = root.children().position(|x| x.tag().name() == "Entry").unwrap();
+        root.remove_child(idx);
+    }).unwrap();
+    assert_eq!(other.entries().len(), 1);
+
+    other.import_xml(&mut Cursor::new(xml)).unwrap();
+    let copy = open(&save(&other));
+
+    assert_eq!(copy.entries().len(), 2);
+    let entry = &copy.entries()[1];
+    assert_eq!(entry.field("UserName"), Some("Michael321"));
+    assert_eq!(entry.field("Password"), Some("12345"));
+    assert!(entry.is_protected("Password"));
+}
+
+#[test]
+fn export_kdbx4() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(v4()).unwrap();
+    db.binaries.push(Binary { protected: true, data: b"attachment".to_vec() });
+
+    let xml = export(&db);
+    let doc = Element::from_reader(&mut Cursor::new(xml)).unwrap();
+
+    let binaries = doc.find("Meta").unwrap().find("Binaries").unwrap();
+    let binary = binaries.find("Binary").unwrap();
+    assert_eq!(binary.get_attr("ID"), Some("0"));
+    assert_eq!(binary.get_attr("ProtectInMemory"), Some("True"));
+
+    let created = doc.find("Root").unwrap()
+                     .find("Group").unwrap()
+                     .find("Times").unwrap()
+                     .find("CreationTime").unwrap()
+                     .text();
+    assert!(created.contains('-'));
+
+    // Exporting leaves the database itself alone.
+    assert_eq!(db.binaries.len(), 1);
+    assert!(db.xml().find("Meta").unwrap().find("Binaries").is_none());
+}
+
+#[test]
+fn import_kdbx4() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(v4()).unwrap();
+    db.binaries.push(Binary { protected: true, data: b"attachment".to_vec() });
+    let xml = export(&db);
+
+    let mut other = open(DB_AES256_GZIP);
+    other.set_version(v4()).unwrap();
+    other.import_xml(&mut Cursor::new(xml)).unwrap();
+
+    assert_eq!(other.binaries, db.binaries);
+    assert!(other.xml().find("Meta").unwrap().find("Binaries").is_none());
+
+    let copy = open(&save(&other));
+    assert_eq!(copy.binaries, db.binaries);
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+    assert_eq!(copy.entries()[0].times().unwrap(), db.entries()[0].times().unwrap());
+}
+
+#[test]
+fn import_invalid() {
+    let mut db = open(DB_AES256_GZIP);
+    let before = export(&db);
+
+    let xml = "<KeePassFile><Meta /></KeePassFile>";
+    assert!(db.import_xml(&mut Cursor::new(xml)).is_err());
+    assert!(db.import_xml(&mut Cursor::new("not xml")).is_err());
+
+    assert_eq!(export(&db), before);
+}
@@ -0,0 +1,103 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+/// Creates `Infra/Prod` below the root group with one entry in `Prod`,
+/// returning the UUIDs of `Infra`, `Prod` and the entry.
+fn add_nested(db : &mut Database) -> (Uuid, Uuid, Uuid) {
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let infra = db.create_group(&root, "Infra").unwrap();
+    let prod = db.create_group(&infra, "Prod").unwrap();
+    let entry = db.create_entry(&prod).unwrap();
+    (infra, prod, entry)
+}
+
+#[test]
+fn searching_is_inherited() {
+    let mut db = open(DB_AES256_PLAIN);
+    let (infra, prod, entry) = add_nested(&mut db);
+
+    assert!(db.searching_enabled(&entry).unwrap());
+    assert_eq!(db.searchable_entries().len(), 3);
+
+    db.group_mut(&infra).unwrap().set_enable_searching(Some(false));
+    assert!(!db.searching_enabled(&infra).unwrap());
+    assert!(!db.searching_enabled(&prod).unwrap());
+    assert!(!db.searching_enabled(&entry).unwrap());
+    assert_eq!(db.searchable_entries().len(), 2);
+
+    db.group_mut(&prod).unwrap().set_enable_searching(Some(true));
+    assert!(db.searching_enabled(&entry).unwrap());
+
+    db.group_mut(&prod).unwrap().set_enable_searching(None);
+    assert_eq!(db.group(&prod).unwrap().enable_searching(), None);
+    assert!(!db.searching_enabled(&entry).unwrap());
+
+    assert!(db.searching_enabled(&Uuid::nil()).is_err());
+}
+
+#[test]
+fn recycled_entries_are_not_searchable() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    db.delete_entry(&uuid).unwrap();
+
+    assert!(!db.searching_enabled(&uuid).unwrap());
+    assert_eq!(db.searchable_entries().len(), 1);
+}
+
+#[test]
+fn auto_type_is_inherited_by_groups() {
+    let mut db = open(DB_AES256_PLAIN);
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let (infra, prod, entry) = add_nested(&mut db);
+
+    db.group_mut(&infra).unwrap().set_enable_auto_type(Some(false));
+
+    assert!(db.auto_type_enabled(&root).unwrap());
+    assert!(!db.auto_type_enabled(&infra).unwrap());
+    assert!(!db.auto_type_enabled(&prod).unwrap());
+    assert!(!db.auto_type_enabled(&entry).unwrap());
+}
+
+#[test]
+fn default_sequence_is_inherited_by_groups() {
+    let mut db = open(DB_AES256_PLAIN);
+    let (infra, prod, entry) = add_nested(&mut db);
+
+    assert_eq!(db.default_auto_type_sequence(&prod).unwrap(),
+               "{USERNAME}{TAB}{PASSWORD}{ENTER}");
+
+    db.group_mut(&infra).unwrap().set_default_auto_type_sequence(Some("{PASSWORD}"));
+    assert_eq!(db.default_auto_type_sequence(&prod).unwrap(), "{PASSWORD}");
+    assert_eq!(db.default_auto_type_sequence(&entry).unwrap(), "{PASSWORD}");
+
+    db.group_mut(&infra).unwrap().set_default_auto_type_sequence(None);
+    assert_eq!(db.group(&infra).unwrap().default_auto_type_sequence(), None);
+}
+
+#[test]
+fn expanded_and_top_visible_entry() {
+    let mut db = open(DB_AES256_PLAIN);
+    let (infra, _, _) = add_nested(&mut db);
+    let entry = db.entries()[0].uuid().unwrap();
+
+    assert!(db.group(&infra).unwrap().is_expanded());
+    assert_eq!(db.group(&infra).unwrap().last_top_visible_entry(), None);
+
+    {
+        let mut group = db.group_mut(&infra).unwrap();
+        group.set_expanded(false);
+        group.set_last_top_visible_entry(Some(&entry));
+    }
+
+    let group = db.group(&infra).unwrap();
+    assert!(!group.is_expanded());
+    assert_eq!(group.last_top_visible_entry(), Some(entry));
+    Database::validate_xml(db.xml()).unwrap();
+}
@@ -0,0 +1,295 @@
+extern crate chrono;
+extern crate elementtree;
+extern crate hex;
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::io::Cursor;
+
+use chrono::{TimeZone, Utc};
+use hex::FromHex;
+
+use kdbx::*;
+use common::{DB_AES256_GZIP, PASSWORD, key, open, save_fixed};
+
+/// A KDBX 4.0 file with Argon2id and ChaCha20, written by
+/// `samples/kdbx4.py` rather than by this crate.
+const DB_ARGON2ID_CHACHA20 : &'static [u8] =
+    include_bytes!("samples/Argon2id.ChaCha20.kdbx");
+
+/// The same as `DB_ARGON2ID_CHACHA20`, but KDBX 4.1.
+const DB_ARGON2ID_CHACHA20_41 : &'static [u8] =
+    include_bytes!("samples/Argon2id.ChaCha20.KDBX41.kdbx");
+
+/// Opens the KDBX 3.1 sample and switches it to KDBX 4 with Argon2 and
+/// ChaCha20, using cheap parameters to keep the tests fast.
+fn open4() -> Database {
+    let mut db = open(DB_AES256_GZIP);
+    db.version = Version { major: 4, minor: 0 };
+    db.kdf = Kdf::Argon2 {
+        variant: Argon2Variant::Argon2d,
+        memory: 1024 * 1024,
+        parallelism: 2,
+        version: 0x13,
+    };
+    db.transform_rounds = 2;
+    db.inner_stream_cipher = InnerStreamCipher::ChaCha20;
+    db.inner_stream_key = Some((0..64).collect());
+    db.stream_start_bytes = vec![];
+    db
+}
+
+#[test]
+fn round_trip_argon2() {
+    let db = open4();
+    let copy = open(&save_fixed(&db));
+
+    assert_eq!(copy.version, db.version);
+    assert_eq!(copy.compression, db.compression);
+    assert_eq!(copy.kdf, db.kdf);
+    assert_eq!(copy.master_seed, db.master_seed);
+    assert_eq!(copy.transform_seed, db.transform_seed);
+    assert_eq!(copy.transform_rounds, 2);
+    assert_eq!(copy.encryption_iv, db.encryption_iv);
+    assert_eq!(copy.inner_stream_cipher, InnerStreamCipher::ChaCha20);
+    assert_eq!(copy.inner_stream_key, db.inner_stream_key);
+
+    let entry = &copy.entries()[1];
+    assert_eq!(entry.field("UserName"), Some("Michael321"));
+    assert_eq!(entry.field("Password"), Some("12345"));
+}
+
+fn check_sample(db : &Database) {
+    assert_eq!(db.version, Version { major: 4, minor: 0 });
+    assert_eq!(db.outer_cipher, OuterCipher::ChaCha20);
+    assert_eq!(db.compression, Compression::GZip);
+    assert_eq!(db.inner_stream_cipher, InnerStreamCipher::ChaCha20);
+    assert_eq!(db.kdf, Kdf::Argon2 {
+        variant: Argon2Variant::Argon2id,
+        memory: 1024 * 1024,
+        parallelism: 2,
+        version: 0x13,
+    });
+
+    assert_eq!(db.binaries, vec![
+        Binary { protected: false, data: b"attached note\n".to_vec() },
+        Binary { protected: true, data: (0..32).collect() },
+    ]);
+
+    let entries = db.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].field("Title"), Some("First Entry"));
+    assert_eq!(entries[0].field("Password"), Some("correct horse"));
+    assert_eq!(entries[0].history()[0].field("Password"), Some("old password"));
+    assert_eq!(entries[1].field("Password"), Some("battery staple"));
+
+    let times = entries[1].times().unwrap();
+    assert_eq!(times.creation_time, Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).single());
+    assert_eq!(times.expiry_time, Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).single());
+}
+
+#[test]
+fn sample_argon2id_chacha20() {
+    let db = open(DB_ARGON2ID_CHACHA20);
+    check_sample(&db);
+
+    let mut out = Vec::new();
+    Writer::new(key(PASSWORD)).write_to(&db, &mut out).unwrap();
+    let copy = open(&out);
+    check_sample(&copy);
+    assert_ne!(copy.master_seed, db.master_seed);
+}
+
+#[test]
+fn sample_kdbx41() {
+    let db = open(DB_ARGON2ID_CHACHA20_41);
+    assert_eq!(db.version, Version { major: 4, minor: 1 });
+
+    let entry = &db.entries()[0];
+    assert_eq!(entry.field("Password"), Some("correct horse"));
+    assert!(!entry.quality_check());
+    assert!(entry.previous_parent_group().is_some());
+    let data = entry.custom_data().unwrap();
+    assert_eq!(data.get("Plugin/Entry"), Some("1"));
+    assert!(data.get_item("Plugin/Entry").unwrap().last_modification_time.is_some());
+
+    let copy = open(&save_fixed(&db));
+    assert_eq!(copy.version, db.version);
+    assert_eq!(copy.binaries, db.binaries);
+    assert_eq!(copy.entries()[0].previous_parent_group(), entry.previous_parent_group());
+}
+
+#[test]
+fn fresh_seeds() {
+    let db = open4();
+
+    let mut out = Vec::new();
+    Writer::new(key(PASSWORD)).write_to(&db, &mut out).unwrap();
+    let copy = open(&out);
+
+    assert_ne!(copy.master_seed, db.master_seed);
+    assert_ne!(copy.transform_seed, db.transform_seed);
+    assert_ne!(copy.encryption_iv, db.encryption_iv);
+    assert_ne!(copy.inner_stream_key, db.inner_stream_key);
+    assert_eq!(copy.inner_stream_key.as_ref().unwrap().len(), 64);
+    assert!(copy.stream_start_bytes.is_empty());
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+}
+
+#[test]
+fn round_trip_aes_kdf() {
+    let mut db = open(DB_AES256_GZIP);
+    db.version = Version { major: 4, minor: 0 };
+    db.compression = Compression::None;
+
+    let copy = open(&save_fixed(&db));
+
+    assert_eq!(copy.kdf, Kdf::Aes);
+    assert_eq!(copy.transform_rounds, db.transform_rounds);
+    assert_eq!(copy.inner_stream_cipher, InnerStreamCipher::Salsa20);
+    assert_eq!(copy.entries()[0].field("Password"), Some("Password"));
+}
+
+#[test]
+fn no_header_hash() {
+    let copy = open(&save_fixed(&open4()));
+    let meta = copy.xml().find("Meta").unwrap();
+
+    assert!(meta.find("HeaderHash").is_none());
+}
+
+#[test]
+fn binaries() {
+    let mut db = open4();
+    db.binaries.push(Binary { protected: true, data: b"secret".to_vec() });
+    db.binaries.push(Binary { protected: false, data: vec![] });
+
+    let copy = open(&save_fixed(&db));
+    assert_eq!(copy.binaries, db.binaries);
+}
+
+fn creation_time(db : &Database) -> String {
+    db.xml()
+      .find("Root").unwrap()
+      .find("Group").unwrap()
+      .find("Times").unwrap()
+      .find("CreationTime").unwrap()
+      .text()
+      .to_owned()
+}
+
+/// Setting `version` directly skips `set_version`, so the writer has to put
+/// the timestamps and binaries in the KDBX 4 layout itself.
+#[test]
+fn version_set_directly() {
+    let mut db = open4();
+    db.binaries.push(Binary { protected: true, data: b"header".to_vec() });
+    db.edit_xml(|doc| {
+        let pool = doc.find_mut("Meta").unwrap().find_mut("Binaries").unwrap();
+        let mut binary = elementtree::Element::new("Binary");
+        binary.set_attr("ID", "0");
+        binary.set_text("bWV0YQ==");
+        pool.append_child(binary);
+    }).unwrap();
+    assert!(creation_time(&db).contains('-'));
+
+    let copy = open(&save_fixed(&db));
+    assert!(!creation_time(&copy).contains('-'));
+    assert!(copy.xml().find("Meta").unwrap().find("Binaries").is_none());
+    assert_eq!(copy.binaries, vec![
+        Binary { protected: true, data: b"header".to_vec() },
+        Binary { protected: false, data: b"meta".to_vec() },
+    ]);
+    assert_eq!(copy.entries()[0].times().unwrap(), db.entries()[0].times().unwrap());
+}
+
+#[test]
+fn version_set_directly_v3() {
+    let mut db = open(DB_AES256_GZIP);
+    db.binaries.push(Binary { protected: false, data: b"header".to_vec() });
+
+    let copy = open(&save_fixed(&db));
+    assert!(creation_time(&copy).contains('-'));
+    assert!(copy.binaries.is_empty());
+
+    let pool = copy.xml().find("Meta").unwrap().find("Binaries").unwrap();
+    assert_eq!(pool.find_all("Binary").count(), 1);
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+}
+
+#[test]
+fn public_custom_data() {
+    let mut db = open4();
+    db.public_custom_data.set("Plugin/Flag", Variant::Bool(true));
+    db.public_custom_data.set("Plugin/Name", Variant::String("kdbx".to_owned()));
+    db.public_custom_data.set("Plugin/Count", Variant::Int64(-3));
+
+    let copy = open(&save_fixed(&db));
+    assert_eq!(copy.public_custom_data, db.public_custom_data);
+}
+
+#[test]
+fn wrong_key() {
+    let bytes = save_fixed(&open4());
+
+    let result = Reader::new(key("wrong")).read_from(&mut Cursor::new(&bytes[..]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn corrupt_block() {
+    let mut bytes = save_fixed(&open4());
+    let last = bytes.len() - 100;
+    bytes[last] ^= 0x01;
+
+    let result = Reader::new(key(PASSWORD)).read_from(&mut Cursor::new(&bytes[..]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn argon2_needs_kdbx4() {
+    let mut db = open4();
+    db.version = Version { major: 3, minor: 1 };
+
+    let mut out = Vec::new();
+    assert!(Writer::new(key(PASSWORD)).write_to(&db, &mut out).is_err());
+}
+
+/// Checks how the KDBX parameters reach Argon2: memory in bytes, rounds as
+/// iterations, and the composite key as the password. The expected value was
+/// computed separately with another Argon2 implementation.
+#[test]
+fn argon2id_transform() {
+    let kdf = Kdf::Argon2 {
+        variant: Argon2Variant::Argon2id,
+        memory: 64 * 1024,
+        parallelism: 2,
+        version: 0x13,
+    };
+
+    let expected = Vec::from_hex("9d65b655f81d8f4d91e7d37610315208\
+                                  256fad5bbc38e315732376f85da6bfda").unwrap();
+    assert_eq!(kdf.transform(&key(PASSWORD), &vec![7; 32], 2).unwrap(), expected);
+}
+
+#[test]
+fn variant_dictionary() {
+    let mut dict = VariantDictionary::new();
+    dict.set("A", Variant::UInt32(1));
+    dict.set("B", Variant::UInt64(2));
+    dict.set("C", Variant::Int32(-3));
+    dict.set("D", Variant::Bytes(vec![4, 5]));
+    dict.set("A", Variant::UInt32(6));
+
+    let bytes = dict.to_bytes();
+    assert_eq!(&bytes[..2], &[0x00, 0x01]);
+    assert_eq!(bytes[bytes.len() - 1], 0);
+
+    let copy = VariantDictionary::from_bytes(&bytes).unwrap();
+    assert_eq!(copy, dict);
+    assert_eq!(copy.get_u32("A"), Some(6));
+    assert_eq!(copy.items()[0].0, "A");
+    assert_eq!(copy.get_bytes("D"), Some(&[4u8, 5][..]));
+}
@@ -25,6 +25,37 @@ fn file_key_from_detect_xml() {
                Some(FileKey::new(FileKeyFormat::Xml, XML_KEY.to_vec())));
 }
 
+const XML2_KEY_IN : &'static [u8] = include_bytes!("sample-key2.xml");
+
+#[test]
+fn file_key_from_xml2() {
+    assert_eq!(FileKey::from_xml(XML2_KEY_IN),
+               Some(FileKey::new(FileKeyFormat::Xml2, XML_KEY.to_vec())));
+}
+
+#[test]
+fn file_key_from_detect_xml2() {
+    assert_eq!(FileKey::from(XML2_KEY_IN).ok(),
+               Some(FileKey::new(FileKeyFormat::Xml2, XML_KEY.to_vec())));
+}
+
+#[test]
+fn file_key_xml2_without_hash() {
+    let input = String::from_utf8(XML2_KEY_IN.to_vec()).unwrap()
+                       .replace(r#" Hash="4016D280""#, "");
+    assert_eq!(FileKey::from(input.as_bytes()).ok(),
+               Some(FileKey::new(FileKeyFormat::Xml2, XML_KEY.to_vec())));
+}
+
+#[test]
+fn file_key_xml2_checksum_mismatch() {
+    let input = String::from_utf8(XML2_KEY_IN.to_vec()).unwrap()
+                       .replace("A817A9D0", "A817A9D1");
+    let err = FileKey::from(input.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("checksum mismatch"));
+    assert_eq!(FileKey::from_xml(input.as_bytes()), None);
+}
+
 const BIN32_KEY : &'static [u8] = &[1,  2,  3,  4,  5,  6,  7,  8,
                                     9,  10, 11, 12, 13, 14, 15, 16,
                                     17, 18, 19, 20, 21, 22, 23, 24,
@@ -66,6 +97,37 @@ fn file_key_from_detect_hex64() {
                Some(FileKey::new(FileKeyFormat::Hex64, HEX64_KEY.to_vec())));
 }
 
+const HELLO_WORLD_SHA256_BYTES : &'static [u8] =
+    &[0xb9, 0x4d, 0x27, 0xb9, 0x93, 0x4d, 0x3e, 0x08,
+      0xa5, 0x2e, 0x52, 0xd7, 0xda, 0x7d, 0xab, 0xfa,
+      0xc4, 0x84, 0xef, 0xe3, 0x7a, 0x53, 0x80, 0xee,
+      0x90, 0x88, 0xf7, 0xac, 0xe2, 0xef, 0xcd, 0xe9];
+
+#[test]
+fn file_key_from_hashed() {
+    assert_eq!(FileKey::from_hashed(b"hello world"),
+               FileKey::new(FileKeyFormat::Hashed, HELLO_WORLD_SHA256_BYTES.to_vec()));
+}
+
+#[test]
+fn file_key_from_detect_hashed() {
+    assert_eq!(FileKey::from(b"hello world").ok(),
+               Some(FileKey::new(FileKeyFormat::Hashed, HELLO_WORLD_SHA256_BYTES.to_vec())));
+}
+
+#[test]
+fn file_key_from_detect_hashed_not_hex() {
+    // 64 bytes, but not hex.
+    let input = [b'z'; 64];
+    assert_eq!(FileKey::from(&input).ok(), Some(FileKey::from_hashed(&input)));
+}
+
+#[test]
+fn file_key_from_detect_hashed_other_xml() {
+    let input = b"<?xml version=\"1.0\"?><Photo />";
+    assert_eq!(FileKey::from(input).ok(), Some(FileKey::from_hashed(input)));
+}
+
 #[test]
 fn file_key_bytes() {
     let fkey = FileKey::new(FileKeyFormat::Bin32, BIN32_KEY.to_vec());
@@ -78,6 +140,15 @@ fn file_key_save_xml() {
     assert_eq!(FileKey::from(&to_save.save()).ok(), Some(to_save));
 }
 
+#[test]
+fn file_key_save_xml2() {
+    let to_save = FileKey::new(FileKeyFormat::Xml2, XML_KEY.to_vec());
+    let saved = to_save.save();
+    assert!(String::from_utf8(saved.clone()).unwrap()
+                   .contains("A817A9D0 CC69C9A0 DE7C6D07 14D4412F"));
+    assert_eq!(FileKey::from(&saved).ok(), Some(to_save));
+}
+
 #[test]
 fn file_key_save_bin32() {
     let to_save = FileKey::new(FileKeyFormat::Bin32, XML_KEY.to_vec());
@@ -94,6 +165,42 @@ fn file_key_save_hex64() {
     assert_eq!(FileKey::from(&saved).ok(), Some(to_save));
 }
 
+#[test]
+fn file_key_save_hashed() {
+    let to_save = FileKey::from_hashed(b"hello world");
+    let saved = to_save.save();
+    assert_eq!(saved, HELLO_WORLD_SHA256_BYTES);
+    assert_eq!(FileKey::from(&saved).unwrap().bytes(), to_save.bytes());
+}
+
+#[test]
+fn file_key_generate() {
+    let formats = vec![FileKeyFormat::Xml, FileKeyFormat::Xml2,
+                       FileKeyFormat::Bin32, FileKeyFormat::Hex64];
+
+    for fmt in formats {
+        let generated = FileKey::generate(fmt).unwrap();
+        assert_eq!(generated.bytes().len(), 32);
+        assert_eq!(FileKey::from(&generated.save()).ok(), Some(generated));
+    }
+}
+
+#[test]
+fn file_key_generate_is_random() {
+    let first = FileKey::generate(FileKeyFormat::Bin32).unwrap();
+    let second = FileKey::generate(FileKeyFormat::Bin32).unwrap();
+    assert_ne!(first, second);
+
+    let first = FileKey::generate_with_entropy(FileKeyFormat::Bin32, b"abc").unwrap();
+    let second = FileKey::generate_with_entropy(FileKeyFormat::Bin32, b"abc").unwrap();
+    assert_ne!(first, second);
+}
+
+#[test]
+fn file_key_generate_hashed() {
+    assert!(FileKey::generate(FileKeyFormat::Hashed).is_err());
+}
+
 ////
 //// CompositeKey
 ////
@@ -0,0 +1,117 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, encrypt_v3, open};
+
+fn group_named(db : &Database, path : &str) -> Uuid {
+    db.group_by_path(&format!("NewDatabase/{}", path), &PathSyntax::new())
+      .unwrap()
+      .uuid()
+      .unwrap()
+}
+
+fn group_names(group : Group) -> Vec<String> {
+    group.groups()
+         .iter()
+         .map(|x| x.name().unwrap().to_owned())
+         .collect()
+}
+
+#[test]
+fn move_entry_updates_location() {
+    let mut db = open(DB_AES256_PLAIN);
+    let general = group_named(&db, "General");
+    let uuid = db.entries()[0].uuid().unwrap();
+    let before = db.entry(&uuid).unwrap().times().unwrap();
+
+    db.move_entry(&uuid, &general).unwrap();
+
+    let entry = db.entry_by_path("NewDatabase/General/Sample Entry", &PathSyntax::new())
+                  .unwrap();
+    let after = entry.times().unwrap();
+    assert!(after.location_changed > before.location_changed);
+    assert_eq!(after.creation_time, before.creation_time);
+    assert_eq!(entry.previous_parent_group(), None);
+
+    assert_eq!(db.root_group().unwrap().entries().len(), 1);
+    Database::validate_xml(db.xml()).unwrap();
+}
+
+#[test]
+fn move_records_previous_parent_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.version = Version { major: 4, minor: 1 };
+
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let general = group_named(&db, "General");
+    let network = group_named(&db, "Network");
+    let before = db.group(&network).unwrap().times().unwrap();
+
+    db.move_group(&network, &general).unwrap();
+
+    let group = db.group(&network).unwrap();
+    assert_eq!(group.previous_parent_group(), Some(root));
+    assert!(group.times().unwrap().location_changed > before.location_changed);
+}
+
+#[test]
+fn move_keeps_sibling_order() {
+    let mut db = open(DB_AES256_PLAIN);
+    let general = group_named(&db, "General");
+    let network = group_named(&db, "Network");
+    let email = group_named(&db, "eMail");
+
+    db.move_group(&network, &general).unwrap();
+    db.move_group(&email, &general).unwrap();
+
+    assert_eq!(group_names(db.root_group().unwrap()),
+               vec!["General", "Windows", "Internet", "Homebanking"]);
+    assert_eq!(group_names(db.group(&general).unwrap()),
+               vec!["Network", "eMail"]);
+
+    let root = db.root_group().unwrap().uuid().unwrap();
+    db.move_group(&general, &root).unwrap();
+    assert_eq!(group_names(db.root_group().unwrap()),
+               vec!["General", "Windows", "Internet", "Homebanking"]);
+}
+
+#[test]
+fn move_keeps_entry_with_malformed_times() {
+    let xml = r#"<KeePassFile><Meta /><Root><Group>
+        <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID>
+        <Entry>
+            <UUID>AAAAAAAAAAAAAAAAAAAAAQ==</UUID>
+            <Times><CreationTime>garbage</CreationTime></Times>
+        </Entry>
+        <Group><UUID>AAAAAAAAAAAAAAAAAAAAAg==</UUID></Group>
+    </Group></Root></KeePassFile>"#;
+
+    let mut db = open(&encrypt_v3(xml));
+    let uuid = db.entries()[0].uuid().unwrap();
+    let group = db.root_group().unwrap().groups()[0].uuid().unwrap();
+
+    assert!(db.move_entry(&uuid, &group).is_err());
+    assert!(db.entry(&uuid).is_some());
+    assert!(db.deleted_objects().unwrap().is_empty());
+}
+
+#[test]
+fn move_refuses_cycles() {
+    let mut db = open(DB_AES256_PLAIN);
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let general = group_named(&db, "General");
+    let network = group_named(&db, "Network");
+
+    db.move_group(&network, &general).unwrap();
+
+    assert!(db.move_group(&general, &general).is_err());
+    assert!(db.move_group(&general, &network).is_err());
+    assert!(db.move_group(&root, &general).is_err());
+    assert!(db.move_group(&general, &Uuid::nil()).is_err());
+
+    assert_eq!(db.path_of(&network, &PathSyntax::new()).unwrap(),
+               "NewDatabase/General/Network");
+}
@@ -0,0 +1,80 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+/// Renames the group called `from` to `to`.
+fn rename_group(db : &mut Database, from : &str, to : &str) {
+    let to = to.to_owned();
+    db.edit_xml(move |doc| {
+        let root = doc.find_mut("Root").unwrap().find_mut("Group").unwrap();
+        for group in root.find_all_mut("Group") {
+            let name = group.find_mut("Name").unwrap();
+            if name.text() == from {
+                name.set_text(to.clone());
+            }
+        }
+    }).unwrap();
+}
+
+#[test]
+fn path_syntax_split_and_join() {
+    let syntax = PathSyntax::new();
+
+    assert_eq!(syntax.split("Root/a\\/b/c").unwrap(), vec!["Root", "a/b", "c"]);
+    assert_eq!(syntax.join(&["Root", "a/b", "c\\d"]), "Root/a\\/b/c\\\\d");
+    assert!(syntax.split("Root//c").is_err());
+    assert!(syntax.split("Root/c\\").is_err());
+
+    let plain = PathSyntax { separator: ':', escape: None };
+    assert_eq!(plain.split("Root:a/b").unwrap(), vec!["Root", "a/b"]);
+}
+
+#[test]
+fn group_and_entry_by_path() {
+    let db = open(DB_AES256_PLAIN);
+    let syntax = PathSyntax::new();
+
+    let group = db.group_by_path("NewDatabase/Internet", &syntax).unwrap();
+    assert_eq!(group.name(), Some("Internet"));
+
+    let entry = db.entry_by_path("NewDatabase/Sample Entry #2", &syntax).unwrap();
+    assert_eq!(entry.field("UserName"), Some("Michael321"));
+
+    assert!(db.group_by_path("NewDatabase/Missing", &syntax).is_err());
+    assert!(db.entry_by_path("Other/Sample Entry", &syntax).is_err());
+}
+
+#[test]
+fn path_with_escaped_separator() {
+    let mut db = open(DB_AES256_PLAIN);
+    rename_group(&mut db, "eMail", "Mail/Calendar");
+
+    let syntax = PathSyntax::new();
+    let group = db.group_by_path("NewDatabase/Mail\\/Calendar", &syntax).unwrap();
+    let uuid = group.uuid().unwrap();
+
+    assert_eq!(db.path_of(&uuid, &syntax).unwrap(), "NewDatabase/Mail\\/Calendar");
+}
+
+#[test]
+fn path_ambiguous() {
+    let mut db = open(DB_AES256_PLAIN);
+    rename_group(&mut db, "eMail", "Internet");
+
+    let result = db.group_by_path("NewDatabase/Internet", &PathSyntax::new());
+    assert_eq!(result.unwrap_err().to_string(), "ambiguous path");
+}
+
+#[test]
+fn path_of_entry() {
+    let db = open(DB_AES256_PLAIN);
+    let syntax = PathSyntax::new();
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    assert_eq!(db.path_of(&uuid, &syntax).unwrap(), "NewDatabase/Sample Entry");
+    assert_eq!(db.path_of(&Uuid::nil(), &syntax), None);
+}
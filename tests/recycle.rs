@@ -0,0 +1,140 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, encrypt_v3, open};
+
+fn group_named(db : &Database, name : &str) -> Uuid {
+    db.group_by_path(&format!("NewDatabase/{}", name), &PathSyntax::new())
+      .unwrap()
+      .uuid()
+      .unwrap()
+}
+
+#[test]
+fn delete_entry_creates_recycle_bin() {
+    let mut db = open(DB_AES256_PLAIN);
+    assert!(db.recycle_bin().is_none());
+
+    let uuid = db.entries()[0].uuid().unwrap();
+    db.delete_entry(&uuid).unwrap();
+
+    let bin = db.recycle_bin().unwrap();
+    assert_eq!(bin.name(), Some("Recycle Bin"));
+    assert_eq!(bin.enable_auto_type(), Some(false));
+    assert_eq!(bin.entries()[0].uuid(), Some(uuid));
+    assert!(db.meta().unwrap().recycle_bin_changed().unwrap().is_some());
+    assert!(db.deleted_objects().unwrap().is_empty());
+
+    Database::validate_xml(db.xml()).unwrap();
+}
+
+#[test]
+fn delete_from_recycle_bin_is_permanent() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    db.delete_entry(&uuid).unwrap();
+    db.delete_entry(&uuid).unwrap();
+
+    assert!(db.entry(&uuid).is_none());
+    assert_eq!(db.deleted_objects().unwrap()[0].uuid, uuid);
+}
+
+#[test]
+fn delete_keeps_entry_with_malformed_times() {
+    let xml = r#"<KeePassFile><Meta>
+        <RecycleBinEnabled>True</RecycleBinEnabled>
+    </Meta><Root><Group>
+        <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID>
+        <Entry>
+            <UUID>AAAAAAAAAAAAAAAAAAAAAQ==</UUID>
+            <Times><CreationTime>garbage</CreationTime></Times>
+        </Entry>
+    </Group></Root></KeePassFile>"#;
+
+    let mut db = open(&encrypt_v3(xml));
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    assert!(db.delete_entry(&uuid).is_err());
+    assert!(db.entry(&uuid).is_some());
+    assert!(db.deleted_objects().unwrap().is_empty());
+}
+
+#[test]
+fn delete_with_recycle_bin_disabled() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.meta_mut().unwrap().set_recycle_bin_enabled(false);
+
+    let group = group_named(&db, "Windows");
+    db.delete_group(&group).unwrap();
+
+    assert!(db.recycle_bin().is_none());
+    assert!(db.group(&group).is_none());
+    assert_eq!(db.deleted_objects().unwrap().len(), 1);
+}
+
+#[test]
+fn delete_recycle_bin_itself() {
+    let mut db = open(DB_AES256_PLAIN);
+    let group = group_named(&db, "Windows");
+    db.delete_group(&group).unwrap();
+
+    let bin = db.recycle_bin().unwrap().uuid().unwrap();
+    db.delete_group(&bin).unwrap();
+
+    assert!(db.recycle_bin().is_none());
+    assert!(db.group(&group).is_none());
+    assert_eq!(db.deleted_objects().unwrap().len(), 2);
+}
+
+#[test]
+fn restore_to_root_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let group = group_named(&db, "Windows");
+
+    db.delete_group(&group).unwrap();
+    assert!(db.restore(&root).is_err());
+    db.restore(&group).unwrap();
+
+    assert_eq!(db.path_of(&group, &PathSyntax::new()).unwrap(), "NewDatabase/Windows");
+    assert!(db.restore(&group).is_err());
+}
+
+#[test]
+fn restore_to_previous_parent_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.version = Version { major: 4, minor: 1 };
+
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let general = group_named(&db, "General");
+    let uuid = db.entries()[1].uuid().unwrap();
+
+    db.delete_entry(&uuid).unwrap();
+    assert_eq!(db.entry(&uuid).unwrap().previous_parent_group(), Some(root));
+
+    db.entry_mut(&uuid).unwrap().set_previous_parent_group(Some(&general)).unwrap();
+    db.restore(&uuid).unwrap();
+
+    assert_eq!(db.path_of(&uuid, &PathSyntax::new()).unwrap(),
+               "NewDatabase/General/Sample Entry #2");
+}
+
+#[test]
+fn empty_recycle_bin() {
+    let mut db = open(DB_AES256_PLAIN);
+    let entry = db.entries()[0].uuid().unwrap();
+    let group = group_named(&db, "eMail");
+
+    db.delete_entry(&entry).unwrap();
+    db.delete_group(&group).unwrap();
+    db.empty_recycle_bin().unwrap();
+
+    let bin = db.recycle_bin().unwrap();
+    assert!(bin.entries().is_empty());
+    assert!(bin.groups().is_empty());
+    assert_eq!(db.deleted_objects().unwrap().len(), 2);
+}
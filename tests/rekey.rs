@@ -0,0 +1,68 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::io::Cursor;
+
+use kdbx::*;
+use common::{DB_AES256_GZIP, PASSWORD, key, open, save};
+
+fn open_with(bytes : &[u8], password : &str) -> Result<Database, Error> {
+    Reader::new(key(password)).read_from(&mut Cursor::new(bytes))
+}
+
+#[test]
+fn write_with_opening_key() {
+    let db = open(DB_AES256_GZIP);
+    let copy = open(&save(&db));
+
+    assert_eq!(copy.entries().len(), 2);
+}
+
+#[test]
+fn rekey() {
+    let mut db = open(DB_AES256_GZIP);
+    let changed = db.meta().unwrap().master_key_changed().unwrap().unwrap();
+    let seed = db.master_seed.clone();
+
+    db.rekey(key("correct horse"), Some(&key(PASSWORD))).unwrap();
+
+    assert!(db.meta().unwrap().master_key_changed().unwrap().unwrap() > changed);
+    assert_ne!(db.master_seed, seed);
+
+    let bytes = save(&db);
+    assert!(open_with(&bytes, PASSWORD).is_err());
+
+    let copy = open_with(&bytes, "correct horse").unwrap();
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+}
+
+#[test]
+fn rekey_without_confirmation() {
+    let mut db = open(DB_AES256_GZIP);
+    db.rekey(key("correct horse"), None).unwrap();
+
+    assert!(open_with(&save(&db), "correct horse").is_ok());
+}
+
+#[test]
+fn rekey_twice() {
+    let mut db = open(DB_AES256_GZIP);
+    db.rekey(key("first"), Some(&key(PASSWORD))).unwrap();
+
+    assert!(db.rekey(key("second"), Some(&key(PASSWORD))).is_err());
+    db.rekey(key("second"), Some(&key("first"))).unwrap();
+
+    assert!(open_with(&save(&db), "second").is_ok());
+}
+
+#[test]
+fn rekey_wrong_current_key() {
+    let mut db = open(DB_AES256_GZIP);
+    let seed = db.master_seed.clone();
+
+    assert!(db.rekey(key("correct horse"), Some(&key("wrong"))).is_err());
+    assert_eq!(db.master_seed, seed);
+    assert!(open_with(&save(&db), PASSWORD).is_ok());
+}
@@ -0,0 +1,139 @@
+extern crate kdbx;
+extern crate openssl;
+extern crate elementtree;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use elementtree::Element;
+use kdbx::*;
+use common::{DB_AES256_GZIP, open, save_fixed};
+
+/// The XML without the header hash, which changes whenever the header does.
+fn xml_string(db : &Database) -> String {
+    let mut doc = db.xml().clone();
+    let meta = doc.find_mut("Meta").unwrap();
+    if let Some(idx) = meta.children().position(|x| x.tag().name() == "HeaderHash") {
+        meta.remove_child(idx);
+    }
+    doc.to_string().unwrap()
+}
+
+/// Checks that read -> write -> read gives back the same database, and that
+/// writing the copy again gives the same bytes.
+fn assert_round_trip(db : &Database) {
+    let bytes = save_fixed(db);
+    let copy = open(&bytes);
+
+    assert_eq!(copy.version, db.version);
+    assert_eq!(copy.other_headers, db.other_headers);
+    assert_eq!(copy.public_custom_data, db.public_custom_data);
+    assert_eq!(copy.binaries, db.binaries);
+    assert_eq!(copy.other_inner_headers, db.other_inner_headers);
+    assert_eq!(xml_string(&copy), xml_string(db));
+    assert_eq!(save_fixed(&copy), bytes);
+}
+
+/// Every sample in `tests/samples`, all with the same master key. New
+/// samples, such as ones from KeePassXC or KeeWeb, are picked up here.
+#[test]
+fn corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/samples");
+
+    let mut count = 0;
+    for item in fs::read_dir(dir).unwrap() {
+        let path = item.unwrap().path();
+        if path.extension().map(|x| x != "kdbx").unwrap_or(true) {
+            continue;
+        }
+
+        assert_round_trip(&open(&fs::read(&path).unwrap()));
+        count += 1;
+    }
+
+    assert!(count >= 4);
+}
+
+#[test]
+fn unknown_elements() {
+    let mut db = open(DB_AES256_GZIP);
+
+    db.edit_xml(|doc| {
+        let mut plugin = Element::new("PluginData");
+        plugin.set_attr("Format", "2");
+        plugin.append_new_child("Nested").set_text("value");
+
+        let meta = doc.find_mut("Meta").unwrap();
+        insert_child(meta, 1, plugin);
+        meta.set_attr("Plugin", "yes");
+
+        let root = doc.find_mut("Root").unwrap();
+        root.append_new_child("FutureRootElement").set_text("x");
+
+        let group = root.find_mut("Group").unwrap();
+        group.set_attr("Unknown", "attribute");
+
+        let mut field = Element::new("FutureEntryField");
+        field.set_attr("Flag", "1");
+        insert_child(group.find_mut("Entry").unwrap(), 2, field);
+    }).unwrap();
+
+    assert_round_trip(&db);
+
+    let copy = open(&save_fixed(&db));
+    let meta = copy.xml().find("Meta").unwrap();
+    assert_eq!(meta.get_attr("Plugin"), Some("yes"));
+
+    let plugin = meta.children().nth(1).unwrap();
+    assert_eq!(plugin.tag().name(), "PluginData");
+    assert_eq!(plugin.get_attr("Format"), Some("2"));
+    assert_eq!(plugin.find("Nested").unwrap().text(), "value");
+
+    let entry = copy.entries()[0].uuid().unwrap();
+    assert_eq!(copy.entry(&entry).unwrap().field("Title"), Some("Sample Entry"));
+}
+
+#[test]
+fn unknown_time_elements() {
+    let mut db = open(DB_AES256_GZIP);
+    db.edit_xml(|doc| {
+        let meta = doc.find_mut("Meta").unwrap();
+        meta.append_new_child("PluginSyncTime").set_text("2017-01-02T03:04:05.678Z");
+        meta.append_new_child("PluginChanged").set_text("AAAAAAAAAAA=");
+    }).unwrap();
+
+    assert_round_trip(&db);
+
+    let copy = open(&save_fixed(&db));
+    let meta = copy.xml().find("Meta").unwrap();
+    assert_eq!(meta.find("PluginSyncTime").unwrap().text(), "2017-01-02T03:04:05.678Z");
+    assert_eq!(meta.find("PluginChanged").unwrap().text(), "AAAAAAAAAAA=");
+}
+
+#[test]
+fn unknown_headers() {
+    let mut db = open(DB_AES256_GZIP);
+    db.other_headers.insert(0, (Header::Comment as u8, b"first".to_vec()));
+    db.other_headers.insert(1, (0x80, b"second".to_vec()));
+    db.other_headers.insert(2, (0x7F, b"third".to_vec()));
+
+    assert_round_trip(&db);
+
+    let copy = open(&save_fixed(&db));
+    let ids : Vec<u8> = copy.other_headers.iter().map(|x| x.0).collect();
+    assert_eq!(ids, vec![Header::Comment as u8, 0x80, 0x7F, Header::End as u8]);
+}
+
+#[test]
+fn unknown_kdbx4_headers() {
+    let mut db = open(DB_AES256_GZIP);
+    db.set_version(Version { major: 4, minor: 0 }).unwrap();
+    db.public_custom_data.set("Plugin", Variant::UInt32(7));
+    db.binaries.push(Binary { protected: false, data: b"attachment".to_vec() });
+    db.other_inner_headers.push((0x7F, b"inner".to_vec()));
+    db.other_headers.insert(0, (0x80, b"outer".to_vec()));
+
+    assert_round_trip(&db);
+}
@@ -0,0 +1,304 @@
+#!/usr/bin/env python3
+"""Writes the KDBX 4 samples in this directory.
+
+This is an implementation of the format separate from the crate, built on the
+`cryptography` package, so the samples check the reader and writer against
+something other than themselves. The seeds are fixed, so running it again
+gives the same files.
+
+    python3 kdbx4.py
+"""
+
+import base64
+import gzip
+import hashlib
+import hmac
+import struct
+from datetime import datetime, timezone
+
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2id
+
+PASSWORD = b"hello world"
+
+CIPHER_CHACHA20 = bytes.fromhex("d6038a2b8b6f4cb5a524339a31dbb59a")
+KDF_ARGON2ID = bytes.fromhex("9e298b1956db4773b23dfc3ec6f0a1e6")
+
+# Cheaper than the KeePass defaults, so the tests stay fast.
+ARGON2_MEMORY = 1024 * 1024
+ARGON2_ITERATIONS = 2
+ARGON2_PARALLELISM = 2
+
+EPOCH = datetime(1, 1, 1, tzinfo=timezone.utc)
+NOW = datetime(2026, 10, 18, 12, 0, 0, tzinfo=timezone.utc)
+
+
+def seed(name, length):
+    return hashlib.sha512(name.encode()).digest()[:length]
+
+
+def u32(x):
+    return struct.pack("<I", x)
+
+
+def u64(x):
+    return struct.pack("<Q", x)
+
+
+def time(t=NOW):
+    seconds = int((t - EPOCH).total_seconds())
+    return base64.b64encode(struct.pack("<q", seconds)).decode()
+
+
+def uuid(name):
+    return base64.b64encode(seed(name, 16)).decode()
+
+
+def chacha20(key, nonce, data):
+    c = Cipher(algorithms.ChaCha20(key, u32(0) + nonce), None).encryptor()
+    return c.update(data)
+
+
+def variant_dictionary(items):
+    out = struct.pack("<H", 0x0100)
+    for kind, name, value in items:
+        name = name.encode()
+        out += bytes([kind]) + struct.pack("<i", len(name)) + name
+        out += struct.pack("<i", len(value)) + value
+    return out + b"\x00"
+
+
+def header(fields):
+    out = b""
+    for id, data in fields:
+        out += bytes([id]) + u32(len(data)) + data
+    return out
+
+
+def block_key(base_key, index):
+    return hashlib.sha512(u64(index) + base_key).digest()
+
+
+def times(expires=False):
+    return f"""<Times>
+  <CreationTime>{time()}</CreationTime>
+  <LastModificationTime>{time()}</LastModificationTime>
+  <LastAccessTime>{time()}</LastAccessTime>
+  <ExpiryTime>{time(datetime(2030, 1, 1, tzinfo=timezone.utc))}</ExpiryTime>
+  <Expires>{expires}</Expires>
+  <UsageCount>3</UsageCount>
+  <LocationChanged>{time()}</LocationChanged>
+</Times>"""
+
+
+def string(key, value, protected=False):
+    attr = ' Protected="True"' if protected else ""
+    return f"<String><Key>{key}</Key><Value{attr}>{value}</Value></String>"
+
+
+def entry(name, title, password, minor, extra=""):
+    v41 = ""
+    if minor >= 1:
+        v41 = f"""<QualityCheck>False</QualityCheck>
+<PreviousParentGroup>{uuid("group:old")}</PreviousParentGroup>"""
+
+    return f"""<Entry>
+<UUID>{uuid(name)}</UUID>
+<IconID>0</IconID>
+<ForegroundColor />
+<BackgroundColor />
+<OverrideURL />
+{v41}
+<Tags>sample;kdbx4</Tags>
+{times()}
+{string("Notes", "Written by kdbx4.py")}
+{string("Password", password, True)}
+{string("Title", title)}
+{string("URL", "https://example.com/")}
+{string("UserName", "user")}
+{extra}
+<AutoType>
+  <Enabled>True</Enabled>
+  <DataTransferObfuscation>0</DataTransferObfuscation>
+  <Association>
+    <Window>*{title}*</Window>
+    <KeystrokeSequence />
+  </Association>
+</AutoType>
+"""
+
+
+def custom_data(key, value, minor):
+    modified = ""
+    if minor >= 1:
+        modified = f"<LastModificationTime>{time()}</LastModificationTime>"
+    return f"""<CustomData>
+<Item><Key>{key}</Key><Value>{value}</Value>{modified}</Item>
+</CustomData>"""
+
+
+def document(minor):
+    group_tags = "<Tags>sample</Tags>" if minor >= 1 else ""
+
+    history = entry("entry:first", "First Entry", "old password", minor) + "</Entry>"
+    first = entry("entry:first", "First Entry", "correct horse", minor,
+                  '<Binary><Key>note.txt</Key><Value Ref="0" /></Binary>'
+                  + custom_data("Plugin/Entry", "1", minor))
+    first += f"<History>{history}</History></Entry>"
+
+    second = entry("entry:second", "Second Entry", "battery staple", minor,
+                   '<Binary><Key>key.bin</Key><Value Ref="1" /></Binary>')
+    second += "<History /></Entry>"
+
+    return f"""<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+<Meta>
+  <Generator>kdbx4.py</Generator>
+  <DatabaseName>KDBX 4.{minor} Sample</DatabaseName>
+  <DatabaseNameChanged>{time()}</DatabaseNameChanged>
+  <DatabaseDescription />
+  <DatabaseDescriptionChanged>{time()}</DatabaseDescriptionChanged>
+  <DefaultUserName />
+  <DefaultUserNameChanged>{time()}</DefaultUserNameChanged>
+  <MaintenanceHistoryDays>365</MaintenanceHistoryDays>
+  <Color />
+  <MasterKeyChanged>{time()}</MasterKeyChanged>
+  <MasterKeyChangeRec>-1</MasterKeyChangeRec>
+  <MasterKeyChangeForce>-1</MasterKeyChangeForce>
+  <MemoryProtection>
+    <ProtectTitle>False</ProtectTitle>
+    <ProtectUserName>False</ProtectUserName>
+    <ProtectPassword>True</ProtectPassword>
+    <ProtectURL>False</ProtectURL>
+    <ProtectNotes>False</ProtectNotes>
+  </MemoryProtection>
+  <RecycleBinEnabled>True</RecycleBinEnabled>
+  <RecycleBinUUID>AAAAAAAAAAAAAAAAAAAAAA==</RecycleBinUUID>
+  <RecycleBinChanged>{time()}</RecycleBinChanged>
+  <EntryTemplatesGroup>AAAAAAAAAAAAAAAAAAAAAA==</EntryTemplatesGroup>
+  <EntryTemplatesGroupChanged>{time()}</EntryTemplatesGroupChanged>
+  <HistoryMaxItems>10</HistoryMaxItems>
+  <HistoryMaxSize>6291456</HistoryMaxSize>
+  <LastSelectedGroup>{uuid("group:root")}</LastSelectedGroup>
+  <LastTopVisibleGroup>{uuid("group:root")}</LastTopVisibleGroup>
+  {custom_data("Plugin/Database", "enabled", minor)}
+</Meta>
+<Root>
+<Group>
+  <UUID>{uuid("group:root")}</UUID>
+  <Name>Root</Name>
+  <Notes />
+  <IconID>49</IconID>
+  {times()}
+  <IsExpanded>True</IsExpanded>
+  <DefaultAutoTypeSequence />
+  <EnableAutoType>null</EnableAutoType>
+  <EnableSearching>null</EnableSearching>
+  <LastTopVisibleEntry>{uuid("entry:first")}</LastTopVisibleEntry>
+  {first}
+  <Group>
+    <UUID>{uuid("group:child")}</UUID>
+    <Name>Child</Name>
+    <Notes />
+    <IconID>48</IconID>
+    {times()}
+    <IsExpanded>True</IsExpanded>
+    <DefaultAutoTypeSequence />
+    <EnableAutoType>null</EnableAutoType>
+    <EnableSearching>null</EnableSearching>
+    <LastTopVisibleEntry>AAAAAAAAAAAAAAAAAAAAAA==</LastTopVisibleEntry>
+    {group_tags}
+    {custom_data("Plugin/Group", "2", minor)}
+    {second}
+  </Group>
+</Group>
+<DeletedObjects>
+  <DeletedObject>
+    <UUID>{uuid("entry:deleted")}</UUID>
+    <DeletionTime>{time()}</DeletionTime>
+  </DeletedObject>
+</DeletedObjects>
+</Root>
+</KeePassFile>
+"""
+
+
+def protect(xml, stream):
+    """Encrypts the protected values in document order."""
+    out = ""
+    marker = '<Value Protected="True">'
+    while marker in xml:
+        head, xml = xml.split(marker, 1)
+        value, xml = xml.split("</Value>", 1)
+        value = base64.b64encode(stream.update(value.encode())).decode()
+        out += head + marker + value + "</Value>"
+    return out + xml
+
+
+def write(path, minor):
+    master_seed = seed("master seed", 32)
+    kdf_seed = seed("kdf seed", 32)
+    iv = seed("iv", 12)
+    stream_key = seed("stream key", 64)
+
+    params = variant_dictionary([
+        (0x42, "$UUID", KDF_ARGON2ID),
+        (0x42, "S", kdf_seed),
+        (0x04, "P", u32(ARGON2_PARALLELISM)),
+        (0x05, "M", u64(ARGON2_MEMORY)),
+        (0x05, "I", u64(ARGON2_ITERATIONS)),
+        (0x04, "V", u32(0x13)),
+    ])
+
+    head = b"\x03\xd9\xa2\x9a\x67\xfb\x4b\xb5" + struct.pack("<HH", minor, 4)
+    head += header([
+        (2, CIPHER_CHACHA20),
+        (3, u32(1)),
+        (4, master_seed),
+        (7, iv),
+        (11, params),
+        (0, b"\r\n\r\n"),
+    ])
+
+    composite = hashlib.sha256(hashlib.sha256(PASSWORD).digest()).digest()
+    transformed = Argon2id(salt=kdf_seed, length=32,
+                           iterations=ARGON2_ITERATIONS,
+                           lanes=ARGON2_PARALLELISM,
+                           memory_cost=ARGON2_MEMORY // 1024).derive(composite)
+    master_key = hashlib.sha256(master_seed + transformed).digest()
+    hmac_base = hashlib.sha512(master_seed + transformed + b"\x01").digest()
+
+    stream_hash = hashlib.sha512(stream_key).digest()
+    stream = Cipher(algorithms.ChaCha20(stream_hash[:32],
+                                        u32(0) + stream_hash[32:44]),
+                    None).encryptor()
+
+    inner = header([
+        (1, u32(3)),
+        (2, stream_key),
+        (3, b"\x00" + b"attached note\n"),
+        (3, b"\x01" + bytes(range(32))),
+        (0, b""),
+    ])
+    xml = protect(document(minor), stream).encode()
+
+    payload = gzip.compress(inner + xml, mtime=0)
+    ciphertext = chacha20(master_key, iv, payload)
+
+    out = head + hashlib.sha256(head).digest()
+    out += hmac.new(block_key(hmac_base, 2**64 - 1), head, hashlib.sha256).digest()
+
+    blocks = [ciphertext, b""]
+    for index, block in enumerate(blocks):
+        size = struct.pack("<i", len(block))
+        mac = hmac.new(block_key(hmac_base, index), u64(index) + size + block,
+                       hashlib.sha256).digest()
+        out += mac + size + block
+
+    with open(path, "wb") as f:
+        f.write(out)
+
+
+if __name__ == "__main__":
+    write("Argon2id.ChaCha20.kdbx", 0)
+    write("Argon2id.ChaCha20.KDBX41.kdbx", 1)
@@ -0,0 +1,145 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use kdbx::*;
+use common::{DB_AES256_GZIP, PASSWORD, key, open};
+
+/// Creates an empty directory for one test.
+fn scratch(name : &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("kdbx-save-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn listing(dir : &PathBuf) -> Vec<String> {
+    let mut names : Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn save_new_file() {
+    let dir = scratch("new");
+    let path = dir.join("vault.kdbx");
+
+    let db = open(DB_AES256_GZIP);
+    Writer::new(key(PASSWORD)).save_to_path(&db, &path, 0).unwrap();
+
+    let copy = open(&fs::read(&path).unwrap());
+    assert_eq!(copy.entries().len(), 2);
+    assert_eq!(listing(&dir), vec!["vault.kdbx"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_replaces_file() {
+    let dir = scratch("replace");
+    let path = dir.join("vault.kdbx");
+    fs::write(&path, b"old").unwrap();
+
+    let db = open(DB_AES256_GZIP);
+    Writer::new(key(PASSWORD)).save_to_path(&db, &path, 0).unwrap();
+
+    assert_eq!(open(&fs::read(&path).unwrap()).entries().len(), 2);
+    assert_eq!(listing(&dir), vec!["vault.kdbx"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn save_is_only_readable_by_owner() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch("mode");
+    let path = dir.join("vault.kdbx");
+    fs::write(&path, b"old").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let db = open(DB_AES256_GZIP);
+    Writer::new(key(PASSWORD)).save_to_path(&db, &path, 0).unwrap();
+
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_keeps_backups() {
+    let dir = scratch("backups");
+    let path = dir.join("vault.kdbx");
+    fs::write(dir.join("vault.kdbx.notes.bak"), b"unrelated").unwrap();
+
+    let mut db = open(DB_AES256_GZIP);
+    let writer = Writer::new(key(PASSWORD));
+
+    for i in 0..4 {
+        let uuid = db.entries()[0].uuid().unwrap();
+        db.entry_mut(&uuid).unwrap().set_field("Title", &format!("Save {}", i));
+        writer.save_to_path(&db, &path, 2).unwrap();
+    }
+
+    let names = listing(&dir);
+    assert_eq!(names.len(), 4);
+    assert!(names.contains(&"vault.kdbx.notes.bak".to_owned()));
+
+    // The newest backup holds the previous save.
+    let newest = names.iter().filter(|x| x.ends_with("Z.bak")).last().unwrap();
+    let backup = open(&fs::read(dir.join(newest)).unwrap());
+    assert_eq!(backup.entries()[0].field("Title"), Some("Save 2"));
+
+    let current = open(&fs::read(&path).unwrap());
+    assert_eq!(current.entries()[0].field("Title"), Some("Save 3"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_failure_keeps_old_file() {
+    let dir = scratch("failure");
+    let path = dir.join("vault.kdbx");
+    fs::write(&path, b"old").unwrap();
+
+    let mut db = open(DB_AES256_GZIP);
+    db.version = Version { major: 5, minor: 0 };
+    assert!(Writer::new(key(PASSWORD)).save_to_path(&db, &path, 1).is_err());
+
+    assert_eq!(fs::read(&path).unwrap(), b"old");
+    assert_eq!(listing(&dir), vec!["vault.kdbx"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn save_rename_failure_keeps_backups() {
+    let dir = scratch("rename");
+    let path = dir.join("vault.kdbx");
+    let old = dir.join("vault.kdbx.20170102T030405.000000Z.bak");
+    fs::write(&old, b"old backup").unwrap();
+
+    // Nothing can be renamed over a directory that has files in it.
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("inside"), b"inside").unwrap();
+
+    let db = open(DB_AES256_GZIP);
+    assert!(Writer::new(key(PASSWORD)).save_to_path(&db, &path, 1).is_err());
+
+    assert_eq!(fs::read(&old).unwrap(), b"old backup");
+    assert_eq!(fs::read(path.join("inside")).unwrap(), b"inside");
+    assert_eq!(listing(&dir), vec!["vault.kdbx", "vault.kdbx.20170102T030405.000000Z.bak"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "serde")]
+
+extern crate kdbx;
+extern crate openssl;
+extern crate serde_json;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+#[test]
+fn header_round_trip() {
+    let db = open(DB_AES256_PLAIN);
+    let json = serde_json::to_string(&db.header()).unwrap();
+    let header : DatabaseHeader = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(header, db.header());
+    assert_eq!(header.version, Version { major: 3, minor: 1 });
+    assert_eq!(header.outer_cipher, OuterCipher::Aes128);
+    assert_eq!(header.inner_stream_cipher, InnerStreamCipher::Salsa20);
+}
+
+#[test]
+fn uuid_as_string() {
+    let uuid = Uuid::from_hex("31c1f2e6-bf71-4350-be58-05216afc5aff").unwrap();
+    let json = serde_json::to_string(&uuid).unwrap();
+
+    assert_eq!(json, "\"31c1f2e6-bf71-4350-be58-05216afc5aff\"");
+    assert_eq!(serde_json::from_str::<Uuid>(&json).unwrap(), uuid);
+    assert!(serde_json::from_str::<Uuid>("\"nope\"").is_err());
+}
+
+#[test]
+fn protected_values_redacted_by_default() {
+    let db = open(DB_AES256_PLAIN);
+    let data = db.to_data(ProtectedValues::default()).unwrap();
+    let json = serde_json::to_string(&data).unwrap();
+
+    assert!(!json.contains("12345"));
+
+    let entry = &data.root.entries[1];
+    assert_eq!(entry.field("Password").unwrap().value, None);
+    assert!(entry.field("Password").unwrap().protected);
+    assert_eq!(entry.field("UserName").unwrap().value.as_ref().unwrap(), "Michael321");
+}
+
+#[test]
+fn protected_values_included_on_request() {
+    let db = open(DB_AES256_PLAIN);
+    let data = db.to_data(ProtectedValues::Include).unwrap();
+    let json = serde_json::to_string(&data).unwrap();
+    let back : DatabaseData = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(back, data);
+    assert_eq!(back.root.name, "NewDatabase");
+    assert_eq!(back.root.groups.len(), 6);
+    assert_eq!(back.root.entries[1].field("Password").unwrap().value.as_ref().unwrap(),
+               "12345");
+    assert_eq!(back.meta.history_max_items, Some(10));
+}
@@ -0,0 +1,100 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+#[test]
+fn entry_defaults() {
+    let db = open(DB_AES256_PLAIN);
+    let entry = db.entries()[0];
+
+    assert_eq!(entry.tags(), Vec::<String>::new());
+    assert_eq!(entry.foreground_color(), None);
+    assert_eq!(entry.background_color(), None);
+    assert_eq!(entry.override_url(), None);
+    assert_eq!(entry.quality_check(), true);
+    assert_eq!(entry.previous_parent_group(), None);
+}
+
+#[test]
+fn entry_tag_set() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    {
+        let mut entry = db.entry_mut(&uuid).unwrap();
+        entry.set_tags(&["prod", "db, ", "prod"]);
+
+        assert_eq!(entry.as_entry().tags(), vec!["prod", "db"]);
+        assert!(!entry.add_tag("PROD"));
+        assert!(entry.add_tag("postgres"));
+        assert!(entry.remove_tag("db"));
+        assert!(!entry.remove_tag("db"));
+    }
+
+    let entry = db.entry(&uuid).unwrap();
+    assert_eq!(entry.tags(), vec!["prod", "postgres"]);
+    assert!(entry.has_tag("Postgres"));
+}
+
+#[test]
+fn entry_colors_and_override_url() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.set_version(Version { major: 4, minor: 1 }).unwrap();
+    let uuid = db.entries()[0].uuid().unwrap();
+    let group = db.root_group().unwrap().uuid().unwrap();
+
+    {
+        let mut entry = db.entry_mut(&uuid).unwrap();
+        entry.set_foreground_color(Some("#FF0000"));
+        entry.set_background_color(Some("#00FF00"));
+        entry.set_override_url(Some("cmd://ssh {USERNAME}@{URL:HOST}"));
+        entry.set_quality_check(false).unwrap();
+        entry.set_previous_parent_group(Some(&group)).unwrap();
+    }
+
+    let entry = db.entry(&uuid).unwrap();
+    assert_eq!(entry.foreground_color(), Some("#FF0000"));
+    assert_eq!(entry.background_color(), Some("#00FF00"));
+    assert_eq!(entry.override_url(), Some("cmd://ssh {USERNAME}@{URL:HOST}"));
+    assert_eq!(entry.quality_check(), false);
+    assert_eq!(entry.previous_parent_group(), Some(group));
+}
+
+#[test]
+fn database_tag_counts() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuids : Vec<Uuid> = db.entries().iter().map(|x| x.uuid().unwrap()).collect();
+
+    db.entry_mut(&uuids[0]).unwrap().set_tags(&["web", "Shared"]);
+    db.entry_mut(&uuids[1]).unwrap().set_tags(&["shared"]);
+
+    let tags = db.tags();
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags["Shared"], 2);
+    assert_eq!(tags["web"], 1);
+    assert_eq!(db.entries_with_tag("SHARED").len(), 2);
+}
+
+#[test]
+fn entry_kdbx41_fields_v3() {
+    let mut db = open(DB_AES256_PLAIN);
+    let uuid = db.entries()[0].uuid().unwrap();
+    let group = db.root_group().unwrap().uuid().unwrap();
+
+    {
+        let mut entry = db.entry_mut(&uuid).unwrap();
+        assert!(entry.set_quality_check(false).is_err());
+        assert!(entry.set_previous_parent_group(Some(&group)).is_err());
+        assert!(entry.set_previous_parent_group(None).is_ok());
+    }
+
+    let entry = db.xml().find("Root").unwrap()
+                  .find("Group").unwrap()
+                  .find("Entry").unwrap();
+    assert!(entry.find("QualityCheck").is_none());
+    assert!(entry.find("PreviousParentGroup").is_none());
+}
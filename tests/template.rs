@@ -0,0 +1,75 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, open};
+
+/// Adds a templates group with a "Server" template, returning the UUID of
+/// the template.
+fn add_templates(db : &mut Database) -> Uuid {
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let group = db.create_group(&root, "Templates").unwrap();
+    db.meta_mut().unwrap().set_entry_templates_group(Some(&group));
+
+    let template = db.create_entry(&group).unwrap();
+    db.update_entry(&template, |e| {
+        e.set_field("Title", "Server");
+        e.set_field("Hostname", "");
+        e.set_field("SSH Key", "");
+        e.set_protected("SSH Key", true);
+    }).unwrap();
+
+    template
+}
+
+#[test]
+fn no_templates_group() {
+    let mut db = open(DB_AES256_PLAIN);
+    let root = db.root_group().unwrap().uuid().unwrap();
+    let uuid = db.entries()[0].uuid().unwrap();
+
+    assert!(db.meta().unwrap().entry_templates_group().is_none());
+    assert!(db.entry_templates().is_empty());
+    assert!(db.create_entry_from_template(&uuid, &root).is_err());
+}
+
+#[test]
+fn list_templates() {
+    let mut db = open(DB_AES256_PLAIN);
+    let template = add_templates(&mut db);
+
+    let templates = db.entry_templates();
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].uuid(), Some(template));
+    assert_eq!(templates[0].title(), Some("Server"));
+}
+
+#[test]
+fn create_entry_from_template() {
+    let mut db = open(DB_AES256_PLAIN);
+    let template = add_templates(&mut db);
+    let general = db.group_by_path("NewDatabase/General", &PathSyntax::new())
+                    .unwrap()
+                    .uuid()
+                    .unwrap();
+
+    let uuid = db.create_entry_from_template(&template, &general).unwrap();
+    assert!(uuid != template);
+
+    let entry = db.entry(&uuid).unwrap();
+    assert_eq!(entry.title(), Some(""));
+    assert_eq!(entry.field("Hostname"), Some(""));
+    assert!(entry.is_protected("SSH Key"));
+    assert!(entry.history().is_empty());
+    assert_eq!(entry.times().unwrap().usage_count, 0);
+
+    assert_eq!(db.group(&general).unwrap().entries().len(), 1);
+    assert_eq!(db.entry_templates().len(), 1);
+    Database::validate_xml(db.xml()).unwrap();
+
+    let other = db.entries()[0].uuid().unwrap();
+    assert!(db.create_entry_from_template(&other, &general).is_err());
+    assert!(db.create_entry_from_template(&template, &Uuid::nil()).is_err());
+}
@@ -0,0 +1,173 @@
+extern crate kdbx;
+extern crate chrono;
+extern crate elementtree;
+extern crate openssl;
+
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use elementtree::Element;
+use kdbx::*;
+use common::{DB_AES256_PLAIN, encrypt_v3, open};
+
+const TIMES_V3 : &'static str = r#"<Times>
+    <CreationTime>2017-07-22T16:59:16Z</CreationTime>
+    <LastModificationTime>2017-07-22T16:59:16Z</LastModificationTime>
+    <LastAccessTime>2017-07-22T16:59:16Z</LastAccessTime>
+    <ExpiryTime>2017-07-22T16:58:03Z</ExpiryTime>
+    <Expires>True</Expires>
+    <UsageCount>3</UsageCount>
+    <LocationChanged>2017-07-22T16:59:16Z</LocationChanged>
+</Times>"#;
+
+const TIMES_V4 : &'static str = r#"<Times>
+    <CreationTime>ZHsF0Q4AAAA=</CreationTime>
+    <LastModificationTime>ZHsF0Q4AAAA=</LastModificationTime>
+    <LastAccessTime>ZHsF0Q4AAAA=</LastAccessTime>
+    <ExpiryTime>ZHsF0Q4AAAA=</ExpiryTime>
+    <Expires>False</Expires>
+    <UsageCount>0</UsageCount>
+    <LocationChanged>ZHsF0Q4AAAA=</LocationChanged>
+</Times>"#;
+
+fn entry_xml(uuid : &str, title : &str, expiry : &str) -> String {
+    format!(r#"<Entry>
+        <UUID>{}</UUID>
+        <Times>
+            <ExpiryTime>{}</ExpiryTime>
+            <Expires>True</Expires>
+        </Times>
+        <String><Key>Title</Key><Value>{}</Value></String>
+    </Entry>"#, uuid, expiry, title)
+}
+
+#[test]
+fn times_from_xml_v3() {
+    let elem = Element::from_reader(TIMES_V3.as_bytes()).unwrap();
+    let times = Times::from_xml(&elem).unwrap();
+
+    assert_eq!(times.creation_time, Utc.with_ymd_and_hms(2017, 7, 22, 16, 59, 16).single());
+    assert_eq!(times.expiry_time, Utc.with_ymd_and_hms(2017, 7, 22, 16, 58, 3).single());
+    assert_eq!(times.expires, true);
+    assert_eq!(times.usage_count, 3);
+}
+
+#[test]
+fn times_from_xml_v4() {
+    let elem = Element::from_reader(TIMES_V4.as_bytes()).unwrap();
+    let times = Times::from_xml(&elem).unwrap();
+
+    assert_eq!(times.creation_time, Utc.with_ymd_and_hms(2017, 7, 22, 16, 59, 16).single());
+    assert_eq!(times.expires, false);
+}
+
+#[test]
+fn times_write_xml_round_trip() {
+    let elem = Element::from_reader(TIMES_V3.as_bytes()).unwrap();
+    let times = Times::from_xml(&elem).unwrap();
+
+    for version in &[Version { major: 3, minor: 1 }, Version { major: 4, minor: 0 }] {
+        let mut out = Element::new("Times");
+        times.write_xml(&mut out, version);
+        assert_eq!(Times::from_xml(&out).unwrap(), times);
+    }
+
+    let mut out = Element::new("Times");
+    times.write_xml(&mut out, &Version { major: 4, minor: 0 });
+    assert_eq!(out.find("CreationTime").unwrap().text(), "ZHsF0Q4AAAA=");
+}
+
+#[test]
+fn times_is_expired_at() {
+    let elem = Element::from_reader(TIMES_V3.as_bytes()).unwrap();
+    let times = Times::from_xml(&elem).unwrap();
+
+    let expiry = times.expiry_time.unwrap();
+
+    assert!(times.is_expired_at(&expiry));
+    assert!(!times.is_expired_at(&(expiry - Duration::seconds(1))));
+}
+
+#[test]
+fn times_missing_fields() {
+    let elem = Element::from_reader(&b"<Times><Expires>True</Expires></Times>"[..]).unwrap();
+    let times = Times::from_xml(&elem).unwrap();
+
+    assert_eq!(times.creation_time, None);
+    assert_eq!(times.expiry_time, None);
+    assert!(times.expires);
+    assert!(!times.is_expired_at(&Utc::now()));
+    assert_eq!(Times::from_xml(&elem).unwrap(), times);
+
+    let mut out = Element::new("Times");
+    times.write_xml(&mut out, &Version { major: 3, minor: 1 });
+    assert!(out.find("CreationTime").is_none());
+    assert!(out.find("ExpiryTime").is_none());
+}
+
+#[test]
+fn times_iso_variants() {
+    let expected = Utc.with_ymd_and_hms(2017, 7, 22, 16, 59, 16).single();
+
+    for text in &["2017-07-22T16:59:16Z",
+                  "2017-07-22T18:59:16+02:00",
+                  "2017-07-22T16:59:16",
+                  "2017-07-22T16:59:16.000Z"] {
+        let xml = format!("<Times><CreationTime>{}</CreationTime></Times>", text);
+        let elem = Element::from_reader(xml.as_bytes()).unwrap();
+        assert_eq!(Times::from_xml(&elem).unwrap().creation_time, expected, "{}", text);
+    }
+}
+
+#[test]
+fn times_malformed() {
+    for text in &["2017-07-22", "12:00", "not-a-time", "AAAA"] {
+        let xml = format!("<Times><CreationTime>{}</CreationTime></Times>", text);
+        let elem = Element::from_reader(xml.as_bytes()).unwrap();
+        assert!(Times::from_xml(&elem).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn database_group_times() {
+    let db = open(DB_AES256_PLAIN);
+    let times = db.root_group().unwrap().times().unwrap();
+
+    assert_eq!(times.creation_time, Utc.with_ymd_and_hms(2017, 7, 22, 16, 59, 16).single());
+    assert_eq!(times.expires, false);
+}
+
+#[test]
+fn database_expiry_queries() {
+    let db = open(DB_AES256_PLAIN);
+    let far_future = Utc.with_ymd_and_hms(2100, 1, 1, 0, 0, 0).unwrap();
+
+    assert_eq!(db.entries().len(), 2);
+    assert!(db.entries_expired_at(&far_future).is_empty());
+    assert!(db.entries_expiring_within(&Utc::now(), Duration::days(30)).is_empty());
+}
+
+#[test]
+fn database_expiry_queries_skip_bad_entries() {
+    let xml = format!(r#"<KeePassFile><Meta /><Root><Group>
+        <UUID>AAAAAAAAAAAAAAAAAAAAAA==</UUID>
+        {}
+        {}
+    </Group></Root></KeePassFile>"#,
+    entry_xml("AAAAAAAAAAAAAAAAAAAAAQ==", "bad", "garbage"),
+    entry_xml("AAAAAAAAAAAAAAAAAAAAAg==", "good", "2017-07-22T16:58:03Z"));
+
+    let db = open(&encrypt_v3(&xml));
+
+    assert!(db.entries()[0].times().is_err());
+
+    let far_future = Utc.with_ymd_and_hms(2100, 1, 1, 0, 0, 0).unwrap();
+    let expired = db.entries_expired_at(&far_future);
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].title(), Some("good"));
+
+    let from = Utc.with_ymd_and_hms(2017, 7, 1, 0, 0, 0).unwrap();
+    let expiring = db.entries_expiring_within(&from, Duration::days(30));
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].title(), Some("good"));
+}
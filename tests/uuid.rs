@@ -0,0 +1,55 @@
+extern crate kdbx;
+
+use kdbx::*;
+
+const CIPHER_AES : [u8; 16] = [0x31, 0xc1, 0xf2, 0xe6,
+                               0xbf, 0x71, 0x43, 0x50,
+                               0xbe, 0x58, 0x05, 0x21,
+                               0x6a, 0xfc, 0x5a, 0xff];
+
+#[test]
+fn uuid_base64() {
+    let uuid = Uuid::from_base64("McHy5r9xQ1C+WAUhavxa/w==").unwrap();
+    assert_eq!(uuid, Uuid(CIPHER_AES));
+    assert_eq!(uuid.to_base64(), "McHy5r9xQ1C+WAUhavxa/w==");
+}
+
+#[test]
+fn uuid_hex() {
+    let uuid = Uuid::from_hex("31c1f2e6-bf71-4350-be58-05216afc5aff").unwrap();
+    assert_eq!(uuid, Uuid(CIPHER_AES));
+    assert_eq!(uuid.to_hex(), "31c1f2e6bf714350be5805216afc5aff");
+    assert_eq!(Uuid::from_hex(&uuid.to_hex()).unwrap(), uuid);
+    assert_eq!(uuid.to_string(), "31c1f2e6-bf71-4350-be58-05216afc5aff");
+}
+
+#[test]
+fn uuid_invalid() {
+    assert!(Uuid::from_base64("AAAA").is_err());
+    assert!(Uuid::from_hex("31c1f2e6").is_err());
+    assert!(Uuid::from_slice(&[0u8; 15]).is_err());
+}
+
+#[test]
+fn uuid_nil() {
+    assert!(Uuid::nil().is_nil());
+    assert_eq!(Uuid::from_base64("AAAAAAAAAAAAAAAAAAAAAA==").unwrap(), Uuid::nil());
+}
+
+#[test]
+fn uuid_new_v4() {
+    let a = Uuid::new_v4().unwrap();
+    let b = Uuid::new_v4().unwrap();
+
+    assert!(a != b);
+    assert_eq!(a.as_bytes()[6] >> 4, 4);
+    assert_eq!(a.as_bytes()[8] >> 6, 2);
+}
+
+#[test]
+fn outer_cipher_uuid() {
+    let uuid = Uuid(CIPHER_AES);
+    assert_eq!(OuterCipher::from(&uuid).unwrap(), OuterCipher::Aes128);
+    assert_eq!(OuterCipher::Aes128.uuid(), uuid);
+    assert!(OuterCipher::from(&Uuid::nil()).is_err());
+}
@@ -0,0 +1,130 @@
+extern crate kdbx;
+extern crate openssl;
+
+mod common;
+
+use std::io::Cursor;
+
+use kdbx::*;
+use common::{DB_AES256_PLAIN, DB_AES256_GZIP, PASSWORD, key, open, save, save_fixed};
+
+fn round_trip(original : &[u8]) {
+    let db = open(original);
+    let copy = open(&save_fixed(&db));
+
+    assert_eq!(copy.version, db.version);
+    assert_eq!(copy.compression, db.compression);
+    assert_eq!(copy.outer_cipher, db.outer_cipher);
+    assert_eq!(copy.master_seed, db.master_seed);
+    assert_eq!(copy.transform_seed, db.transform_seed);
+    assert_eq!(copy.transform_rounds, db.transform_rounds);
+    assert_eq!(copy.encryption_iv, db.encryption_iv);
+    assert_eq!(copy.inner_stream_key, db.inner_stream_key);
+    assert_eq!(copy.inner_stream_cipher, db.inner_stream_cipher);
+    assert_eq!(copy.stream_start_bytes, db.stream_start_bytes);
+    assert_eq!(copy.other_headers, db.other_headers);
+
+    // The header is written exactly as KeePass wrote it, so even the header
+    // hash in the XML stays the same.
+    assert_eq!(copy.xml().to_string().unwrap(), db.xml().to_string().unwrap());
+}
+
+#[test]
+fn round_trip_plain() {
+    round_trip(DB_AES256_PLAIN);
+}
+
+#[test]
+fn round_trip_gzip() {
+    round_trip(DB_AES256_GZIP);
+}
+
+#[test]
+fn fresh_seeds() {
+    let db = open(DB_AES256_GZIP);
+    let first = save(&db);
+    let second = save(&db);
+    assert_ne!(first, second);
+
+    let copy = open(&first);
+    assert_ne!(copy.master_seed, db.master_seed);
+    assert_ne!(copy.transform_seed, db.transform_seed);
+    assert_ne!(copy.encryption_iv, db.encryption_iv);
+    assert_ne!(copy.inner_stream_key, db.inner_stream_key);
+    assert_ne!(copy.stream_start_bytes, db.stream_start_bytes);
+
+    assert_eq!(copy.transform_rounds, db.transform_rounds);
+    assert_eq!(copy.entries()[1].field("Password"), Some("12345"));
+
+    // The seeds in the database itself are left alone.
+    assert_eq!(db.master_seed, open(DB_AES256_GZIP).master_seed);
+}
+
+#[test]
+fn fixed_seeds() {
+    let db = open(DB_AES256_GZIP);
+    assert_eq!(save_fixed(&db), save_fixed(&db));
+}
+
+#[test]
+fn randomize_seeds() {
+    let mut db = open(DB_AES256_PLAIN);
+    let before = db.clone();
+    db.randomize_seeds().unwrap();
+
+    assert_ne!(db.master_seed, before.master_seed);
+    assert_eq!(db.master_seed.len(), 32);
+    assert_eq!(db.transform_seed.len(), 32);
+    assert_eq!(db.encryption_iv.len(), 16);
+    assert_eq!(db.inner_stream_key.as_ref().unwrap().len(), 32);
+    assert_eq!(db.stream_start_bytes.len(), 32);
+
+    let copy = open(&save_fixed(&db));
+    assert_eq!(copy.master_seed, db.master_seed);
+}
+
+#[test]
+fn randomize_seeds_kdbx4() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.set_version(Version { major: 4, minor: 0 }).unwrap();
+    db.outer_cipher = OuterCipher::ChaCha20;
+    db.randomize_seeds().unwrap();
+
+    assert_eq!(db.encryption_iv.len(), 12);
+    assert_eq!(db.inner_stream_key.as_ref().unwrap().len(), 64);
+    assert!(db.stream_start_bytes.is_empty());
+
+    let copy = open(&save_fixed(&db));
+    assert_eq!(copy.encryption_iv, db.encryption_iv);
+}
+
+#[test]
+fn write_changes() {
+    let mut db = open(DB_AES256_GZIP);
+    let uuid = db.entries()[1].uuid().unwrap();
+    db.update_entry(&uuid, |e| e.set_field("Password", "correct horse")).unwrap();
+
+    let copy = open(&save(&db));
+    let entry = copy.entry(&uuid).unwrap();
+
+    assert_eq!(entry.field("Password"), Some("correct horse"));
+    assert_eq!(entry.history()[0].field("Password"), Some("12345"));
+}
+
+#[test]
+fn write_wrong_key() {
+    let db = open(DB_AES256_PLAIN);
+    let bytes = save(&db);
+
+    let result = Reader::new(key("wrong")).read_from(&mut Cursor::new(&bytes[..]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn write_unsupported_version() {
+    let mut db = open(DB_AES256_PLAIN);
+    db.version = Version { major: 5, minor: 0 };
+
+    let mut out = Vec::new();
+    assert!(Writer::new(key(PASSWORD)).write_to(&db, &mut out).is_err());
+}
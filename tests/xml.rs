@@ -0,0 +1,82 @@
+extern crate kdbx;
+extern crate openssl;
+extern crate elementtree;
+
+mod common;
+
+use elementtree::Element;
+use kdbx::*;
+use common::{DB_AES256_PLAIN, DB_AES256_GZIP, open};
+
+#[test]
+fn protected_values_in_plaintext() {
+    for data in &[DB_AES256_PLAIN, DB_AES256_GZIP] {
+        let db = open(data);
+        let entries = db.entries();
+
+        assert_eq!(entries[0].field("Password"), Some("Password"));
+        assert_eq!(entries[1].field("Password"), Some("12345"));
+    }
+}
+
+#[test]
+fn debug_hides_protected_values() {
+    let db = open(DB_AES256_PLAIN);
+    let debug = format!("{:?}", db);
+
+    assert!(debug.starts_with("Database {"));
+    assert!(!debug.contains("12345"));
+    assert!(!debug.contains("xml_doc"));
+    assert!(!debug.contains("inner_stream_key"));
+}
+
+#[test]
+fn xml_accessor() {
+    let db = open(DB_AES256_PLAIN);
+    let name = db.xml().find("Meta").unwrap().find("DatabaseName").unwrap();
+    assert_eq!(name.text(), "Test Database");
+    assert!(Database::validate_xml(db.xml()).is_ok());
+}
+
+#[test]
+fn edit_xml_valid() {
+    let mut db = open(DB_AES256_PLAIN);
+
+    db.edit_xml(|doc| {
+        doc.find_mut("Meta")
+           .unwrap()
+           .find_mut("DatabaseName")
+           .unwrap()
+           .set_text("Renamed");
+    }).unwrap();
+
+    let name = db.xml().find("Meta").unwrap().find("DatabaseName").unwrap();
+    assert_eq!(name.text(), "Renamed");
+}
+
+#[test]
+fn edit_xml_rejects_invalid() {
+    let mut db = open(DB_AES256_PLAIN);
+
+    let result = db.edit_xml(|doc| {
+        let root = doc.find_mut("Root").unwrap();
+        root.append_new_child("Group");
+    });
+    assert!(result.is_err());
+
+    let result = db.edit_xml(|doc| {
+        let group = doc.find_mut("Root").unwrap().find_mut("Group").unwrap();
+        let copy = group.find("Group").unwrap().clone();
+        group.append_child(copy);
+    });
+    assert!(result.is_err());
+
+    // The document is left untouched after a failed edit.
+    assert_eq!(db.root_group().unwrap().groups().len(), 6);
+}
+
+#[test]
+fn set_xml_rejects_wrong_root() {
+    let mut db = open(DB_AES256_PLAIN);
+    assert!(db.set_xml(Element::new("Banana")).is_err());
+}